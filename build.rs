@@ -1,9 +1,61 @@
 use std::env::var;
 use std::fs::File;
 use std::io::{Result, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-fn run_cmake(source_dir: &str) {
+/// Indicates how the `dy` library is linked
+#[derive(Clone, Copy, PartialEq)]
+enum LinkKind {
+    Static,
+    Dynamic,
+}
+
+impl LinkKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            LinkKind::Static => "static",
+            LinkKind::Dynamic => "dylib",
+        }
+    }
+}
+
+/// Reads `DY_STATIC`: `1`, `true` or `yes` selects static linking, anything else dynamic
+fn link_kind_from_env() -> LinkKind {
+    println!("cargo:rerun-if-env-changed=DY_STATIC");
+    match var("DY_STATIC") {
+        Ok(v) if ["1", "true", "yes"].contains(&v.to_lowercase().as_str()) => LinkKind::Static,
+        _ => LinkKind::Dynamic,
+    }
+}
+
+/// Returns the C++ runtime `dy` is written against, which a static `dy` needs
+/// linked explicitly, or `None` where the toolchain links it by itself
+fn cxx_runtime() -> Option<&'static str> {
+    let target_os = var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    let target_env = var("CARGO_CFG_TARGET_ENV").unwrap_or_default();
+    match (target_os.as_str(), target_env.as_str()) {
+        ("macos", _) | ("ios", _) | ("freebsd", _) | ("openbsd", _) => Some("c++"),
+        (_, "msvc") => None,
+        _ => Some("stdc++"),
+    }
+}
+
+/// Links `dy` itself, with the C++ runtime if it is linked statically
+///
+/// # Arguments
+///
+/// * `name` - the name of the library
+/// * `kind` - how to link it
+fn link_dy(name: &str, kind: LinkKind) {
+    println!("cargo:rustc-link-lib={}={}", kind.as_str(), name);
+    if kind == LinkKind::Static {
+        if let Some(runtime) = cxx_runtime() {
+            println!("cargo:rustc-link-lib=dylib={}", runtime);
+        }
+    }
+}
+
+fn run_cmake(source_dir: &str) -> PathBuf {
     let sources = [
         "source/dy.cc",
         "public/dy.h",
@@ -19,14 +71,62 @@ fn run_cmake(source_dir: &str) {
         "cargo:rustc-link-search=native={}/bin",
         install_dir.display()
     );
-    println!("cargo:rustc-link-lib=static={}", source_dir);
-    println!("cargo:rustc-link-lib=dylib={}", source_dir);
+    link_dy(source_dir, link_kind_from_env());
+
+    Path::new(source_dir).join("public/dy.h")
 }
 
-fn generate_bindings(header_path: &str) -> Result<()> {
+/// Links against a `dy` installed in `DY_LIB_DIR`, returning the path of `dy.h`
+///
+/// # Arguments
+///
+/// * `lib_dir` - the directory containing the library
+fn link_from_env(lib_dir: &str) -> PathBuf {
+    println!("cargo:rerun-if-env-changed=DY_INCLUDE_DIR");
+    let include_dir = match var("DY_INCLUDE_DIR") {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => Path::new(lib_dir).join("../include"),
+    };
+
+    println!("cargo:rustc-link-search=native={}", lib_dir);
+    link_dy("dy", link_kind_from_env());
+
+    include_dir.join("dy.h")
+}
+
+/// Links against a `dy` found by `pkg-config`, returning the path of `dy.h`
+fn link_from_pkg_config() -> PathBuf {
+    let kind = link_kind_from_env();
+    let lib = pkg_config::Config::new()
+        .statik(kind == LinkKind::Static)
+        .cargo_metadata(false)
+        .probe("dy")
+        .expect("Unable to find dy with pkg-config");
+
+    for path in lib.link_paths.iter() {
+        println!("cargo:rustc-link-search=native={}", path.display());
+    }
+    for name in lib.libs.iter() {
+        // only `dy` itself follows `DY_STATIC`; its dependencies are left to the system
+        let linked_with_dy = kind == LinkKind::Static && Some(name.as_str()) == cxx_runtime();
+        if name == "dy" {
+            link_dy(name, kind);
+        } else if !linked_with_dy {
+            println!("cargo:rustc-link-lib=dylib={}", name);
+        }
+    }
+
+    lib.include_paths
+        .iter()
+        .map(|dir| dir.join("dy.h"))
+        .find(|header| header.exists())
+        .expect("Unable to find dy.h in the include paths reported by pkg-config")
+}
+
+fn generate_bindings(header_path: &Path) -> Result<()> {
     // generate bindings
     let bindings = bindgen::Builder::default()
-        .header(header_path)
+        .header(header_path.to_string_lossy())
         .parse_callbacks(Box::new(bindgen::CargoCallbacks))
         .generate_comments(false)
         .generate()
@@ -52,6 +152,15 @@ fn generate_bindings(header_path: &str) -> Result<()> {
 fn main() -> Result<()> {
     // Set profile as an environment variable: used to build tests/import_test
     println!("cargo:rustc-env=PROFILE={}", var("PROFILE").unwrap());
-    run_cmake("dy");
-    generate_bindings("dy/public/dy.h")
+
+    // Use an installed dy if requested, otherwise build the bundled sources
+    println!("cargo:rerun-if-env-changed=DY_LIB_DIR");
+    let header_path = if let Ok(lib_dir) = var("DY_LIB_DIR") {
+        link_from_env(&lib_dir)
+    } else if var("CARGO_FEATURE_SYSTEM").is_ok() {
+        link_from_pkg_config()
+    } else {
+        run_cmake("dy")
+    };
+    generate_bindings(&header_path)
 }
@@ -0,0 +1,272 @@
+//! Attribute macros exporting Rust functions through `dy`
+//!
+//! Use them through the `dy` crate with its `export` feature, e.g. `#[dy::export]`,
+//! `#[dy::init]` or `#[dy::shutdown]`.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Error, Expr, FnArg, ItemFn, Lit, Meta, Pat, Type};
+
+/// Checks that a function can be called from an exported wrapper
+fn check_signature(item: &ItemFn) -> Result<(), Error> {
+    let sig = &item.sig;
+    if let Some(asyncness) = &sig.asyncness {
+        return Err(Error::new_spanned(
+            asyncness,
+            "exported functions cannot be async",
+        ));
+    }
+    if !sig.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &sig.generics,
+            "exported functions cannot be generic",
+        ));
+    }
+    if let Some(variadic) = &sig.variadic {
+        return Err(Error::new_spanned(
+            variadic,
+            "exported functions cannot be variadic",
+        ));
+    }
+    for input in sig.inputs.iter() {
+        if let FnArg::Receiver(receiver) = input {
+            return Err(Error::new_spanned(
+                receiver,
+                "exported functions cannot take `self`",
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that a function run by `Module::initialize` or `Module::shutdown`
+/// takes the given number of parameters
+fn check_lifecycle(item: &ItemFn, attr: &str, params: usize, expected: &str) -> Result<(), Error> {
+    check_signature(item)?;
+    if item.sig.inputs.len() != params {
+        return Err(Error::new_spanned(
+            &item.sig,
+            format!("`{}` functions must take {}", attr, expected),
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the types of the parameters and their names used in errors
+fn params(item: &ItemFn) -> (Vec<&Type>, Vec<String>) {
+    let mut types = Vec::new();
+    let mut names = Vec::new();
+    for (idx, input) in item.sig.inputs.iter().enumerate() {
+        if let FnArg::Typed(pat) = input {
+            types.push(pat.ty.as_ref());
+            names.push(match pat.pat.as_ref() {
+                Pat::Ident(ident) => ident.ident.to_string(),
+                _ => idx.to_string(),
+            });
+        }
+    }
+    (types, names)
+}
+
+/// Concatenates the doc comments of a function, one line each
+fn doc(item: &ItemFn) -> String {
+    let mut rtn = String::new();
+    for attr in item.attrs.iter() {
+        if let Meta::NameValue(meta) = &attr.meta {
+            if !meta.path.is_ident("doc") {
+                continue;
+            }
+            if let Expr::Lit(lit) = &meta.value {
+                if let Lit::Str(line) = &lit.lit {
+                    rtn.push_str(&line.value());
+                    rtn.push('\n');
+                }
+            }
+        }
+    }
+    rtn
+}
+
+/// Makes the `extern "C"` function converting the arguments, calling the
+/// original function and catching its panics, and registers it in the manifest
+fn wrapper(item: &ItemFn, symbol: &str) -> TokenStream2 {
+    let name = &item.sig.ident;
+    let wrapper = format_ident!("__dy_export_{}", name);
+    let (types, names) = params(item);
+    let args: Vec<_> = (0..types.len())
+        .map(|idx| format_ident!("__arg{}", idx))
+        .collect();
+    let inputs = &item.sig.inputs;
+    let output = &item.sig.output;
+    let signature = quote!((#inputs) #output).to_string();
+    let doc = doc(item);
+    quote! {
+        ::dy::__export_symbol! {
+            #[export_name = #symbol]
+            #[doc(hidden)]
+            pub unsafe extern "C" fn #wrapper(
+                args: *const ::dy::ValuePtr,
+                len: usize,
+            ) -> ::dy::ValuePtr {
+                ::dy::invoke_exported(args, len, |args| {
+                    let (#(#args,)*) = <(#(#types,)*) as ::dy::FromArgs<'_>>::from_args(
+                        args,
+                        &[#(#names),*],
+                    )?;
+                    ::dy::ExportResult::into_result(#name(#(#args),*))
+                })
+            }
+        }
+
+        ::dy::__register_builtin!(
+            #wrapper,
+            #symbol,
+            <(#(#types,)*) as ::dy::FromArgs<'static>>::ARITY,
+            #signature,
+            #doc,
+        );
+    }
+}
+
+/// Exports a function, so that a host calls it with `Module::get_fn`
+///
+/// The function stays callable from Rust; a hidden `extern "C"` function is
+/// exported under its name. Parameters may be of any `FromArg` type, e.g. a
+/// `FromValue` type or `&str`, whose number and types are checked before the body runs, and a single
+/// `Vec<Borrowed<'_>>` parameter takes the arguments as they are. The function
+/// returns any `ExportResult`. Panics and errors are reported to the host as
+/// error values instead of unwinding across the DLL boundary. Doc comments are
+/// put in the manifest of the DLL.
+///
+/// ```ignore
+/// #[dy::export]
+/// pub fn scale(v: Vec<f64>, k: f64) -> Vec<f64> {
+///     v.into_iter().map(|x| x * k).collect()
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn export(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let attr = TokenStream2::from(attr);
+    if !attr.is_empty() {
+        return Error::new(attr.span(), "`export` takes no arguments")
+            .to_compile_error()
+            .into();
+    }
+    let item = parse_macro_input!(item as ItemFn);
+    if let Err(err) = check_signature(&item) {
+        return err.to_compile_error().into();
+    }
+    let wrapper = wrapper(&item, &item.sig.ident.to_string());
+    (quote! {
+        #item
+        #wrapper
+    })
+    .into()
+}
+
+/// Parses the function of a lifecycle attribute, which takes no arguments
+fn lifecycle_item(
+    attr: TokenStream,
+    item: TokenStream,
+    name: &str,
+    params: usize,
+    expected: &str,
+) -> Result<ItemFn, Error> {
+    let attr = TokenStream2::from(attr);
+    if !attr.is_empty() {
+        return Err(Error::new(
+            attr.span(),
+            format!("`{}` takes no arguments", name),
+        ));
+    }
+    let item = syn::parse::<ItemFn>(item)?;
+    check_lifecycle(&item, name, params, expected)?;
+    Ok(item)
+}
+
+/// Exports the function a host runs with `Module::initialize` before using the DLL
+///
+/// The function takes the configuration passed by the host as a `&Value` and
+/// returns any `ExportResult`; an error or a panic fails `Module::initialize`.
+/// A DLL has at most one such function.
+///
+/// ```ignore
+/// #[dy::init]
+/// fn init(config: &Value) -> Result<(), CallError> {
+///     let path: String = config.as_map().and_then(...)...;
+///     Ok(())
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn init(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item = match lifecycle_item(attr, item, "init", 1, "the configuration as `&Value`") {
+        Ok(item) => item,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let name = &item.sig.ident;
+    let wrapper = format_ident!("__dy_init_{}", name);
+    (quote! {
+        #item
+
+        ::dy::__export_symbol! {
+            #[export_name = "dy_init"]
+            #[doc(hidden)]
+            pub unsafe extern "C" fn #wrapper(
+                args: *const ::dy::ValuePtr,
+                len: usize,
+            ) -> ::dy::ValuePtr {
+                ::dy::invoke_exported(args, len, |args| {
+                    if args.len() != 1 {
+                        return Err(::dy::CallError::from(format!(
+                            "expected 1 argument, found {}",
+                            args.len()
+                        )));
+                    }
+                    ::dy::ExportResult::into_result(#name(&args[0]))
+                })
+            }
+        }
+    })
+    .into()
+}
+
+/// Exports the function a `Module` runs before unloading the DLL
+///
+/// It runs once, when the `Module` is dropped or `Module::shutdown` is called,
+/// unless the DLL has a `#[dy::init]` function which has not succeeded. The
+/// function takes no parameters and returns any `ExportResult`.
+///
+/// ```ignore
+/// #[dy::shutdown]
+/// fn shutdown() {
+///     CACHE.lock().unwrap().clear();
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn shutdown(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item = match lifecycle_item(attr, item, "shutdown", 0, "no parameters") {
+        Ok(item) => item,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let name = &item.sig.ident;
+    let wrapper = format_ident!("__dy_shutdown_{}", name);
+    (quote! {
+        #item
+
+        ::dy::__export_symbol! {
+            #[export_name = "dy_shutdown"]
+            #[doc(hidden)]
+            pub unsafe extern "C" fn #wrapper(
+                args: *const ::dy::ValuePtr,
+                len: usize,
+            ) -> ::dy::ValuePtr {
+                ::dy::invoke_exported(args, len, |_| ::dy::ExportResult::into_result(#name()))
+            }
+        }
+    })
+    .into()
+}
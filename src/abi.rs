@@ -0,0 +1,146 @@
+use crate::bindings::*;
+use std::fmt;
+use std::mem::size_of;
+
+/// The version of the `dy` header this crate is built against
+///
+/// Bump it whenever the bindings to `dy.h` change in an incompatible way.
+pub const HEADER_VERSION: u32 = 1;
+
+/// The name of the symbol returning the `AbiVersion` of a DLL
+pub(crate) const ABI_VERSION_SYMBOL: &str = "dy_abi_version";
+
+/// The tag of an allocator private to a single binary, e.g. a statically linked
+/// C runtime; binaries reporting it never exchange values
+pub const ALLOCATOR_PRIVATE: u32 = 0;
+
+/// The tag of the C library shared by every binary of a Unix process
+pub const ALLOCATOR_LIBC: u32 = 1;
+
+/// The tag of the Universal C Runtime, `ucrtbase.dll`, linked dynamically
+pub const ALLOCATOR_UCRT: u32 = 2;
+
+/// The tag of the legacy Windows C runtime, `msvcrt.dll`, used by MinGW
+pub const ALLOCATOR_MSVCRT: u32 = 3;
+
+/// Returns the tag of the heap the C runtime of the current binary allocates values in
+fn allocator() -> u32 {
+    if cfg!(target_feature = "crt-static") {
+        ALLOCATOR_PRIVATE
+    } else if cfg!(unix) {
+        ALLOCATOR_LIBC
+    } else if cfg!(all(windows, target_env = "msvc")) {
+        ALLOCATOR_UCRT
+    } else if cfg!(all(windows, target_env = "gnu")) {
+        ALLOCATOR_MSVCRT
+    } else {
+        ALLOCATOR_PRIVATE
+    }
+}
+
+/// Describes the binary interface a DLL passes `dy` values with
+///
+/// A host only exchanges values with a DLL reporting the same `AbiVersion`,
+/// unless its allocator is `ALLOCATOR_PRIVATE`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AbiVersion {
+    /// the version of the `dy` header
+    pub header_version: u32,
+    /// the size of a pointer in bytes
+    pub pointer_width: u32,
+    /// the size of a key-value pair of a generic map in bytes
+    pub keyval_size: u32,
+    /// identifies the heap values are allocated in, one of the `ALLOCATOR_*` tags
+    pub allocator: u32,
+}
+
+impl AbiVersion {
+    /// Returns the `AbiVersion` of the current binary
+    pub fn current() -> AbiVersion {
+        AbiVersion {
+            header_version: HEADER_VERSION,
+            pointer_width: size_of::<usize>() as u32,
+            keyval_size: size_of::<dy_keyval_t>() as u32,
+            allocator: allocator(),
+        }
+    }
+
+    /// Returns whether values can be passed between binaries reporting these versions
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - the `AbiVersion` of the other binary
+    pub fn is_compatible(&self, other: &AbiVersion) -> bool {
+        self == other && self.allocator != ALLOCATOR_PRIVATE
+    }
+}
+
+impl fmt::Display for AbiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "header v{}, {}-bit, {}-byte key-value pairs, allocator {}",
+            self.header_version,
+            self.pointer_width * 8,
+            self.keyval_size,
+            self.allocator
+        )
+    }
+}
+
+/// Exports the `AbiVersion` of the DLL so that hosts can verify it when loading
+///
+/// Every DLL linking `dy` with the `export` feature exports it, unless the
+/// `builtin` feature links its functions statically instead.
+#[cfg(feature = "export")]
+#[cfg_attr(not(feature = "builtin"), no_mangle)]
+pub extern "C" fn dy_abi_version() -> AbiVersion {
+    AbiVersion::current()
+}
+
+/// Exports the `AbiVersion` of a DLL so that hosts can verify it when loading
+///
+/// Only needed by crates exporting functions with `exported!` without the
+/// `export` feature, which exports it already; it then expands to nothing.
+/// It also lets the host share its objects passed by id, e.g. cancellation
+/// tokens, with the DLL.
+///
+/// ```ignore
+/// dy::abi_version!();
+/// ```
+#[cfg(not(feature = "export"))]
+#[macro_export]
+macro_rules! abi_version {
+    () => {
+        $crate::__export_symbol! {
+            #[no_mangle]
+            pub extern "C" fn dy_abi_version() -> $crate::AbiVersion {
+                $crate::AbiVersion::current()
+            }
+        }
+
+        $crate::__export_symbol! {
+            #[no_mangle]
+            pub unsafe extern "C" fn dy_attach(table: *const $crate::ObjectTable, owner: u64) {
+                $crate::__attach_objects(table, owner)
+            }
+        }
+    };
+}
+
+/// Exports the `AbiVersion` of a DLL so that hosts can verify it when loading
+///
+/// Only needed by crates exporting functions with `exported!` without the
+/// `export` feature, which exports it already; it then expands to nothing.
+/// It also lets the host share its objects passed by id, e.g. cancellation
+/// tokens, with the DLL.
+///
+/// ```ignore
+/// dy::abi_version!();
+/// ```
+#[cfg(feature = "export")]
+#[macro_export]
+macro_rules! abi_version {
+    () => {};
+}
@@ -0,0 +1,116 @@
+use crate::convert::{FromValue, IntoArgs};
+use crate::error::{into_result, CallError};
+use crate::manifest::FunctionInfo;
+#[cfg(feature = "import")]
+use crate::pool::CallFuture;
+use crate::value::*;
+use std::sync::Arc;
+
+/// Indicates a DLL, or a stand-in for one, whose functions are called by name
+///
+/// Host code written against `ModuleApi` runs the same with a `Module`, a
+/// `SharedModule`, a `RemoteModule` or a `MockModule`.
+pub trait ModuleApi {
+    /// Lists the functions the module declares
+    fn functions(&self) -> Vec<FunctionInfo>;
+
+    /// Returns whether the module exports a function
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the function
+    fn has_fn(&self, name: &str) -> bool;
+
+    /// Invokes a function, returning `None` if the module does not export it
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the function
+    /// * `args` - the arguments
+    fn call_with_borrowed(&self, name: &str, args: &[Borrowed<'_>]) -> Option<Owned>;
+
+    /// Calls a function and disposes arguments after the invocation,
+    /// returning `None` if the module does not export it
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the function
+    /// * `args` - the arguments
+    fn call(&self, name: &str, args: Vec<Owned>) -> Option<Owned> {
+        let borrowed: Vec<Borrowed<'_>> = args.iter().map(|arg| arg.borrow()).collect();
+        self.call_with_borrowed(name, &borrowed)
+    }
+
+    /// Calls a function and disposes arguments after the invocation,
+    /// reporting a missing function, a panic or an error of the function as `CallError`
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the function
+    /// * `args` - the arguments
+    fn try_call(&self, name: &str, args: Vec<Owned>) -> Result<Owned, CallError> {
+        let rtn = self
+            .call(name, args)
+            .ok_or_else(|| CallError::from(format!("no function named `{}`", name)))?;
+        into_result(rtn)
+    }
+
+    /// Calls a function with Rust types, converting the result back
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the function
+    /// * `args` - the arguments, usually a tuple
+    fn call_typed<Args: IntoArgs, Ret: FromValue>(
+        &self,
+        name: &str,
+        args: Args,
+    ) -> Result<Ret, CallError>
+    where
+        Self: Sized,
+    {
+        let rtn = self.try_call(name, args.into_args())?;
+        Ret::from_value(&rtn).map_err(CallError::unexpected_return)
+    }
+
+    /// Calls a function on a worker thread, resolving to its result, or to the
+    /// error value of a `CallError` if the module does not export it
+    ///
+    /// Only modules which are cheap to clone and may move to another thread
+    /// have it, e.g. `SharedModule`, `RemoteModule` or an `Arc` of a module.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the function
+    /// * `args` - the arguments
+    #[cfg(feature = "import")]
+    fn call_async(&self, name: &str, args: Vec<Owned>) -> CallFuture
+    where
+        Self: Clone + Send + Sized + 'static,
+    {
+        let module = self.clone();
+        let name = String::from(name);
+        CallFuture::spawn(move || match module.call(&name, args) {
+            Some(rtn) => rtn,
+            None => CallError::from(format!("no function named `{}`", name)).to_value(),
+        })
+    }
+}
+
+impl<T: ModuleApi + ?Sized> ModuleApi for Arc<T> {
+    fn functions(&self) -> Vec<FunctionInfo> {
+        (**self).functions()
+    }
+
+    fn has_fn(&self, name: &str) -> bool {
+        (**self).has_fn(name)
+    }
+
+    fn call_with_borrowed(&self, name: &str, args: &[Borrowed<'_>]) -> Option<Owned> {
+        (**self).call_with_borrowed(name, args)
+    }
+
+    fn call(&self, name: &str, args: Vec<Owned>) -> Option<Owned> {
+        (**self).call(name, args)
+    }
+}
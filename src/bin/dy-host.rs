@@ -0,0 +1,5 @@
+//! Loads a DLL in its own process on behalf of `dy::remote::RemoteModule`
+
+fn main() {
+    std::process::exit(dy::remote::host_main())
+}
@@ -0,0 +1,258 @@
+//! Inspects and calls DLLs using `dy` from the command line
+
+use dy::codec;
+use dy::text;
+use dy::*;
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+#[cfg(feature = "repl")]
+mod repl;
+
+const USAGE: &str = "\
+usage: dy [<option>...] list <lib>
+       dy [<option>...] call <lib> <fn> [<arg>...]
+       dy convert [--from text|json|binary] [--to text|json|binary] [<input> [<output>]]
+       dy [<option>...] repl [<lib>...]
+
+<lib> is the path to a DLL, or the name of a DLL in the current directory or
+the system search path. Arguments are dy literals, a superset of JSON, e.g.
+'{\"k\": i[1, 2]}'. convert reads standard input and writes standard output
+unless files are given. repl calls functions interactively, see :help.
+
+options:
+  --require-versioned  refuses DLLs which do not export their dy ABI version
+  --config <literal>   initializes every DLL with the given configuration";
+
+/// Indicates a failure reported to the user, with the exit code of the process
+struct Failure {
+    message: String,
+    code: i32,
+}
+
+impl Failure {
+    fn usage(message: &str) -> Failure {
+        Failure {
+            message: format!("{}\n\n{}", message, USAGE),
+            code: 2,
+        }
+    }
+}
+
+impl<E: std::error::Error> From<E> for Failure {
+    fn from(err: E) -> Failure {
+        Failure {
+            message: err.to_string(),
+            code: 1,
+        }
+    }
+}
+
+/// Indicates the options applying to every DLL loaded
+struct Options {
+    allow_unversioned: bool,
+    config: Option<Owned>,
+}
+
+impl Default for Options {
+    /// Loads DLLs the way `ModuleLoader` does by default
+    fn default() -> Options {
+        Options {
+            allow_unversioned: true,
+            config: None,
+        }
+    }
+}
+
+impl Options {
+    /// Removes the options from the arguments
+    fn parse(args: &[String]) -> Result<(Options, Vec<String>), Failure> {
+        let mut options = Options::default();
+        let mut rest = Vec::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--require-versioned" => options.allow_unversioned = false,
+                "--config" => {
+                    let config = args
+                        .next()
+                        .ok_or_else(|| Failure::usage("--config expects a literal"))?;
+                    options.config = Some(text::parse(config).map_err(|err| Failure {
+                        message: format!("--config: {}", err),
+                        code: 2,
+                    })?);
+                }
+                _ => rest.push(arg.clone()),
+            }
+        }
+        Ok((options, rest))
+    }
+}
+
+/// Loads a DLL from a path or by name, initializing it if a configuration is given
+fn load(lib: &str, options: &Options) -> Result<Module, Failure> {
+    let loader = ModuleLoader::new()
+        .dir(".")
+        .system_search(true)
+        .allow_unversioned(options.allow_unversioned);
+    let m = if Path::new(lib).is_file() {
+        loader.open(lib)?
+    } else {
+        loader.load(lib)?
+    };
+    if let Some(config) = &options.config {
+        m.initialize(config).map_err(|err| Failure {
+            message: format!("could not initialize {}: {}", lib, err),
+            code: 1,
+        })?;
+    }
+    Ok(m)
+}
+
+/// Prints the manifest of a DLL, called `lib` in messages
+fn print_functions(lib: &str, m: &Module) {
+    let functions = m.functions();
+    if functions.is_empty() {
+        println!("{} exports no manifest", lib);
+    }
+    for info in functions {
+        match info.signature {
+            Some(signature) => println!("{}{}", info.name, signature),
+            None => println!("{}(..)", info.name),
+        }
+        if let Some(doc) = info.doc {
+            for line in doc.lines() {
+                println!("    {}", line.trim());
+            }
+        }
+    }
+}
+
+fn list(lib: &str, options: &Options) -> Result<(), Failure> {
+    print_functions(lib, &load(lib, options)?);
+    Ok(())
+}
+
+fn call(lib: &str, name: &str, args: &[String], options: &Options) -> Result<(), Failure> {
+    let args = args
+        .iter()
+        .enumerate()
+        .map(|(idx, arg)| {
+            text::parse(arg).map_err(|err| Failure {
+                message: format!("argument {}: {}", idx, err),
+                code: 2,
+            })
+        })
+        .collect::<Result<Vec<Owned>, Failure>>()?;
+    let m = load(lib, options)?;
+    let f = m.get_fn(name).ok_or_else(|| Failure {
+        message: format!("{} does not export `{}`", lib, name),
+        code: 1,
+    })?;
+    let rtn = f.try_call(args)?;
+    println!("{}", text::to_string_pretty(&rtn));
+    Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Text,
+    Json,
+    Binary,
+}
+
+fn format(name: &str) -> Result<Format, Failure> {
+    match name {
+        "text" => Ok(Format::Text),
+        "json" => Ok(Format::Json),
+        "binary" => Ok(Format::Binary),
+        _ => Err(Failure::usage(&format!("unknown format `{}`", name))),
+    }
+}
+
+fn convert(args: &[String]) -> Result<(), Failure> {
+    let mut from = Format::Text;
+    let mut to = Format::Text;
+    let mut files = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--from" | "--to" => {
+                let name = args
+                    .next()
+                    .ok_or_else(|| Failure::usage(&format!("{} expects a format", arg)))?;
+                if arg == "--from" {
+                    from = format(name)?;
+                } else {
+                    to = format(name)?;
+                }
+            }
+            _ => files.push(arg),
+        }
+    }
+    if files.len() > 2 {
+        return Err(Failure::usage("too many files"));
+    }
+
+    let input = match files.first() {
+        Some(path) => fs::read(path)?,
+        None => {
+            let mut input = Vec::new();
+            io::stdin().read_to_end(&mut input)?;
+            input
+        }
+    };
+    let val = match from {
+        Format::Binary => codec::decode(&input)?,
+        Format::Text | Format::Json => {
+            let input = String::from_utf8(input)?;
+            text::parse(&input)?
+        }
+    };
+    let output = match to {
+        Format::Binary => codec::encode(&val),
+        Format::Text => (text::to_string_pretty(&val) + "\n").into_bytes(),
+        Format::Json => (text::to_json(&val) + "\n").into_bytes(),
+    };
+    match files.get(1) {
+        Some(path) => fs::write(path, output)?,
+        None => io::stdout().write_all(&output)?,
+    }
+    Ok(())
+}
+
+fn run(args: &[String]) -> Result<(), Failure> {
+    if let [cmd, args @ ..] = args {
+        if cmd == "convert" {
+            return convert(args);
+        }
+    }
+    let (options, args) = Options::parse(args)?;
+    match args.as_slice() {
+        [cmd, lib] if cmd == "list" => list(lib, &options),
+        [cmd, lib, name, args @ ..] if cmd == "call" => call(lib, name, args, &options),
+        #[cfg(feature = "repl")]
+        [cmd, libs @ ..] if cmd == "repl" => repl::repl(libs, options),
+        #[cfg(not(feature = "repl"))]
+        [cmd, ..] if cmd == "repl" => Err(Failure {
+            message: String::from("dy was built without the `repl` feature"),
+            code: 2,
+        }),
+        [flag] if flag == "-h" || flag == "--help" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        [] => Err(Failure::usage("expected a command")),
+        _ => Err(Failure::usage("invalid arguments")),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(failure) = run(&args) {
+        eprintln!("dy: {}", failure.message);
+        std::process::exit(failure.code);
+    }
+}
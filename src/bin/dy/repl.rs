@@ -0,0 +1,297 @@
+//! The interactive mode of `dy`, calling functions of loaded DLLs line by line
+
+use super::{load, print_functions, Failure, Options};
+use dy::text;
+use dy::*;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::path::Path;
+
+const HELP: &str = "\
+<fn>(<arg>, ...)         calls a function of the first module exporting it
+<module>.<fn>(<arg>, ...) calls a function of the given module
+<literal>                evaluates a dy literal
+:list                    lists the functions of every module
+:load <lib>              loads another DLL
+:help                    shows this message
+:quit                    exits, as does Ctrl-D
+
+Arguments are dy literals, e.g. i[1, 2] or {k: x\"ff\"}. Every result is kept
+in a numbered variable, e.g. $1, which may be used in later arguments.";
+
+const COMMANDS: &[&str] = &[":list", ":load", ":help", ":quit"];
+
+/// Indicates a loaded DLL and the name it is called by
+struct Loaded {
+    name: String,
+    module: Module,
+}
+
+/// Completes commands and the names of exported functions
+struct ReplHelper {
+    names: Vec<String>,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos]
+            .rfind(|c: char| !(c.is_ascii_alphanumeric() || "_.:".contains(c)))
+            .map_or(0, |idx| idx + 1);
+        let word = &line[start..pos];
+        let candidates: Box<dyn Iterator<Item = &str>> = if word.starts_with(':') {
+            Box::new(COMMANDS.iter().copied())
+        } else {
+            Box::new(self.names.iter().map(String::as_str))
+        };
+        let matches = candidates
+            .filter(|name| name.starts_with(word))
+            .map(String::from)
+            .collect();
+        Ok((start, matches))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+/// Names a DLL after its file name without the prefix, the suffix and the
+/// version of the platform, e.g. `foo` for `libfoo.so.1.2`
+fn module_name(lib: &str) -> String {
+    let file_name = Path::new(lib)
+        .file_name()
+        .map_or(lib, |name| name.to_str().unwrap_or(lib));
+    let mut unversioned = file_name;
+    while let Some((rest, version)) = unversioned.rsplit_once('.') {
+        if version.is_empty() || !version.bytes().all(|b| b.is_ascii_digit()) {
+            break;
+        }
+        unversioned = rest;
+    }
+    Naming::platform()
+        .module_name(unversioned)
+        .unwrap_or_else(|| String::from(file_name))
+}
+
+struct Session {
+    modules: Vec<Loaded>,
+    results: Vec<Owned>,
+    options: Options,
+}
+
+impl Session {
+    /// Loads a DLL, refusing DLLs of the same name as a loaded one, which
+    /// could not be told apart in calls
+    fn load(&mut self, lib: &str) -> Result<(), Failure> {
+        let name = module_name(lib);
+        if self.modules.iter().any(|loaded| loaded.name == name) {
+            return Err(Failure {
+                message: format!("a module named `{}` is already loaded", name),
+                code: 1,
+            });
+        }
+        let module = load(lib, &self.options)?;
+        self.modules.push(Loaded { name, module });
+        Ok(())
+    }
+
+    /// Returns the names completed by the editor, bare and qualified
+    fn names(&self) -> Vec<String> {
+        let mut rtn = Vec::new();
+        for loaded in &self.modules {
+            for info in loaded.module.functions() {
+                rtn.push(format!("{}.{}", loaded.name, info.name));
+                rtn.push(info.name);
+            }
+        }
+        rtn.sort();
+        rtn.dedup();
+        rtn
+    }
+
+    fn get_fn(&self, name: &str) -> Result<Function<'_>, String> {
+        // module names may hold dots, function names do not
+        if let Some((module, name)) = name.rsplit_once('.') {
+            let loaded = self
+                .modules
+                .iter()
+                .find(|loaded| loaded.name == module)
+                .ok_or_else(|| format!("no module named `{}`", module))?;
+            return loaded
+                .module
+                .get_fn(name)
+                .ok_or_else(|| format!("{} does not export `{}`", module, name));
+        }
+        self.modules
+            .iter()
+            .find_map(|loaded| loaded.module.get_fn(name))
+            .ok_or_else(|| format!("no module exports `{}`", name))
+    }
+
+    /// Parses a literal, resolving `$1`, `$2`, ... to earlier results
+    fn parse(&self, src: &str) -> Result<Owned, text::ParseError> {
+        text::parse_with(src, |var| {
+            let idx = var.parse::<usize>().ok()?.checked_sub(1)?;
+            self.results.get(idx).map(|val| val.copy())
+        })
+    }
+
+    /// Evaluates a call or a literal, returning its result
+    fn eval(&self, line: &str) -> Result<Owned, String> {
+        let call = line.find('(').filter(|&idx| {
+            let name = line[..idx].trim_end();
+            line.ends_with(')')
+                && !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        });
+        let idx = match call {
+            Some(idx) => idx,
+            None => return self.parse(line).map_err(|err| err.to_string()),
+        };
+
+        let f = self.get_fn(line[..idx].trim_end())?;
+        // parse the arguments as the elements of an array
+        let args = format!("[{}]", &line[idx + 1..line.len() - 1]);
+        let args = self.parse(&args).map_err(|mut err| {
+            err.offset += idx;
+            err.to_string()
+        })?;
+        let args = args
+            .as_arr()
+            .map(|arr| arr.iter().map(|arg| arg.copy()).collect())
+            .unwrap_or_default();
+        f.try_call(args).map_err(|err| format!("error: {}", err))
+    }
+}
+
+/// Returns the type of a value, with the length of arrays and maps
+fn type_name(val: &Value) -> String {
+    match val.as_type() {
+        As::BoolArr(arr) => format!("BoolArr[{}]", arr.len()),
+        As::Bytes(bytes) => format!("Bytes[{}]", bytes.len()),
+        As::IntArr(arr) => format!("IntArr[{}]", arr.len()),
+        As::FloatArr(arr) => format!("FloatArr[{}]", arr.len()),
+        As::Arr(arr) => format!("Arr[{}]", arr.len()),
+        As::Map(map) => format!("Map[{}]", map.size()),
+        _ => format!("{:?}", val.get_type()),
+    }
+}
+
+/// Prints a value with one element of a generic array or map per line, each
+/// followed by its type
+fn write_annotated(out: &mut String, val: &Value, level: usize, comma: bool) {
+    let indent = "  ".repeat(level + 1);
+    let sep = if comma { "," } else { "" };
+    match val.as_type() {
+        As::Arr(arr) if arr.len() > 0 => {
+            out.push_str(&format!("[  // {}\n", type_name(val)));
+            let len = arr.len();
+            for (idx, elem) in arr.iter().enumerate() {
+                out.push_str(&indent);
+                write_annotated(out, &elem, level + 1, idx + 1 < len);
+            }
+            out.push_str(&format!("{}]{}\n", "  ".repeat(level), sep));
+        }
+        As::Map(map) if map.size() > 0 => {
+            out.push_str(&format!("{{  // {}\n", type_name(val)));
+            let len = map.size();
+            for (idx, pair) in map.iter().enumerate() {
+                let key = text::to_string(&Value::new_str(pair.get_key()));
+                out.push_str(&format!("{}{}: ", indent, key));
+                write_annotated(out, pair.get_val(), level + 1, idx + 1 < len);
+            }
+            out.push_str(&format!("{}}}{}\n", "  ".repeat(level), sep));
+        }
+        _ => out.push_str(&format!(
+            "{}{}  // {}\n",
+            text::to_string(val),
+            sep,
+            type_name(val)
+        )),
+    }
+}
+
+/// Runs the interactive mode until the end of the input
+///
+/// # Arguments
+///
+/// * `libs` - the DLLs loaded first
+/// * `options` - the options applying to every DLL loaded
+pub fn repl(libs: &[String], options: Options) -> Result<(), Failure> {
+    let mut session = Session {
+        modules: Vec::new(),
+        results: Vec::new(),
+        options,
+    };
+    for lib in libs {
+        session.load(lib)?;
+    }
+    let mut editor = Editor::<ReplHelper, DefaultHistory>::new()?;
+    editor.set_helper(Some(ReplHelper {
+        names: session.names(),
+    }));
+
+    loop {
+        let line = match editor.readline("dy> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        editor.add_history_entry(line)?;
+
+        match line.split_once(char::is_whitespace).unwrap_or((line, "")) {
+            (":quit", _) | (":q", _) => return Ok(()),
+            (":help", _) => println!("{}", HELP),
+            (":list", _) => {
+                for loaded in &session.modules {
+                    println!("[{}]", loaded.name);
+                    print_functions(&loaded.name, &loaded.module);
+                }
+            }
+            (":load", lib) if !lib.trim().is_empty() => match session.load(lib.trim()) {
+                Ok(()) => {
+                    let names = session.names();
+                    if let Some(helper) = editor.helper_mut() {
+                        helper.names = names;
+                    }
+                }
+                Err(failure) => eprintln!("{}", failure.message),
+            },
+            (cmd, _) if cmd.starts_with(':') => eprintln!("unknown command `{}`, see :help", cmd),
+            _ => match session.eval(line) {
+                Ok(rtn) => {
+                    let mut out = String::new();
+                    write_annotated(&mut out, &rtn, 0, false);
+                    session.results.push(rtn);
+                    print!("${} = {}", session.results.len(), out);
+                }
+                Err(message) => eprintln!("{}", message),
+            },
+        }
+    }
+}
@@ -0,0 +1,233 @@
+#[cfg(all(feature = "builtin", feature = "import"))]
+use crate::api::ModuleApi;
+use crate::exported::RawFunction;
+#[cfg(all(feature = "builtin", feature = "import"))]
+use crate::hook::CallHook;
+#[cfg(all(feature = "builtin", feature = "import"))]
+use crate::import::Function;
+#[cfg(all(feature = "builtin", feature = "import"))]
+use crate::loader::LoadError;
+use crate::manifest::FunctionInfo;
+#[cfg(all(feature = "builtin", feature = "import"))]
+use crate::value::*;
+#[cfg(all(feature = "builtin", feature = "import"))]
+use std::sync::Arc;
+
+/// Indicates a function registered by `#[export]`, `exported!` or `register!`
+/// in a crate linked into the binary
+///
+/// Registered functions are collected at link time. They make the manifest of
+/// a DLL, and a plugin crate linked statically is called through
+/// `StaticModule` the same way as a DLL through `Module`.
+#[derive(Debug, Clone, Copy)]
+pub struct StaticFunction {
+    crate_name: &'static str,
+    name: &'static str,
+    raw: RawFunction,
+    arity: Option<usize>,
+    signature: &'static str,
+    doc: &'static str,
+}
+
+inventory::collect!(StaticFunction);
+
+impl StaticFunction {
+    /// Creates a new `StaticFunction` instance, which `register!` submits
+    ///
+    /// # Arguments
+    ///
+    /// * `crate_name` - the name of the crate defining the function
+    /// * `name` - the name of the function
+    /// * `raw` - the function pointer
+    pub const fn new(crate_name: &'static str, name: &'static str, raw: RawFunction) -> Self {
+        StaticFunction {
+            crate_name,
+            name,
+            raw,
+            arity: None,
+            signature: "",
+            doc: "",
+        }
+    }
+
+    /// Describes the function in the manifest
+    ///
+    /// # Arguments
+    ///
+    /// * `arity` - the number of arguments, `None` if the function is variadic
+    /// * `signature` - the parameters and the return type, as written in Rust
+    /// * `doc` - the documentation, one line per doc comment
+    pub const fn with_info(
+        self,
+        arity: Option<usize>,
+        signature: &'static str,
+        doc: &'static str,
+    ) -> Self {
+        StaticFunction {
+            arity,
+            signature,
+            doc,
+            ..self
+        }
+    }
+
+    /// Returns the name of the crate defining the function
+    pub fn crate_name(&self) -> &'static str {
+        self.crate_name
+    }
+
+    /// Returns the name of the function
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the function pointer
+    pub fn as_raw(&self) -> RawFunction {
+        self.raw
+    }
+
+    /// Returns the description of the function in the manifest
+    pub fn info(&self) -> FunctionInfo {
+        let doc: Vec<&str> = self.doc.lines().map(str::trim).collect();
+        let doc = doc.join("\n");
+        FunctionInfo {
+            name: String::from(self.name),
+            arity: self.arity,
+            signature: Some(tidy_signature(self.signature)).filter(|sig| !sig.is_empty()),
+            doc: Some(String::from(doc.trim())).filter(|doc| !doc.is_empty()),
+        }
+    }
+
+    /// Lists every registered function
+    pub fn all() -> impl Iterator<Item = &'static StaticFunction> {
+        inventory::iter::<StaticFunction>.into_iter()
+    }
+}
+
+/// Removes the spaces `stringify!` puts around punctuation, e.g. turns
+/// `(v : Vec < f64 >) -> f64` into `(v: Vec<f64>) -> f64`
+fn tidy_signature(signature: &str) -> String {
+    let mut rtn = String::with_capacity(signature.len());
+    let mut chars = signature.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ' ' {
+            let prev = rtn.chars().last();
+            let next = chars.peek().copied();
+            let after_open = matches!(prev, Some('<') | Some('(') | Some('[') | Some('&'));
+            let after_path = rtn.ends_with("::");
+            let before_close = matches!(next, Some('<') | Some('>') | Some(')') | Some(']'));
+            let before_punct = matches!(next, Some(',') | Some(':'));
+            if after_open || after_path || before_close || before_punct {
+                continue;
+            }
+        }
+        rtn.push(c);
+    }
+    rtn
+}
+
+/// Indicates the functions registered by crates linked into the binary
+///
+/// It is either every registered function, or those of a single crate. With
+/// the `builtin` feature, `#[export]` and `exported!` export no symbol, so
+/// that crates exporting functions of the same name link into one binary; a
+/// crate built as a DLL must not enable it.
+#[cfg(all(feature = "builtin", feature = "import"))]
+pub struct StaticModule {
+    crate_name: Option<String>,
+    hooks: Vec<Arc<dyn CallHook>>,
+}
+
+#[cfg(all(feature = "builtin", feature = "import"))]
+impl StaticModule {
+    /// Creates a new `StaticModule` instance holding every registered function
+    ///
+    /// Fails with `LoadError::DuplicateFunctions` if several crates register
+    /// functions of the same name, which `new` tells apart.
+    pub fn all() -> Result<StaticModule, LoadError> {
+        let mut names: Vec<&str> = StaticFunction::all().map(|func| func.name).collect();
+        names.sort_unstable();
+        let mut duplicates: Vec<String> = names
+            .windows(2)
+            .filter(|pair| pair[0] == pair[1])
+            .map(|pair| String::from(pair[0]))
+            .collect();
+        duplicates.dedup();
+        if !duplicates.is_empty() {
+            return Err(LoadError::DuplicateFunctions(duplicates));
+        }
+        Ok(StaticModule {
+            crate_name: None,
+            hooks: Vec::new(),
+        })
+    }
+
+    /// Creates a new `StaticModule` instance holding the functions registered
+    /// by a crate
+    ///
+    /// Returns `None` if the crate registered no function or is not linked.
+    ///
+    /// # Arguments
+    ///
+    /// * `crate_name` - the name of the crate, with `-` replaced by `_`
+    pub fn new(crate_name: &str) -> Option<StaticModule> {
+        if StaticFunction::all().any(|func| func.crate_name == crate_name) {
+            Some(StaticModule {
+                crate_name: Some(String::from(crate_name)),
+                hooks: Vec::new(),
+            })
+        } else {
+            None
+        }
+    }
+
+    fn entries(&self) -> impl Iterator<Item = &'static StaticFunction> + '_ {
+        StaticFunction::all().filter(move |func| match &self.crate_name {
+            Some(crate_name) => func.crate_name == crate_name,
+            None => true,
+        })
+    }
+
+    /// Lists the registered functions
+    pub fn functions(&self) -> Vec<FunctionInfo> {
+        self.entries().map(|func| func.info()).collect()
+    }
+
+    /// Retrieves a registered function
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the function
+    pub fn get_fn(&self, name: &str) -> Option<Function<'_>> {
+        let func = self.entries().find(|func| func.name == name)?;
+        Some(Function::with_hooks(func.name, func.raw, &self.hooks))
+    }
+
+    /// Adds a hook observing every call of the functions retrieved afterwards
+    ///
+    /// # Arguments
+    ///
+    /// * `hook` - the hook
+    pub fn add_hook<H: CallHook + 'static>(&mut self, hook: H) {
+        self.hooks.push(Arc::new(hook));
+    }
+}
+
+#[cfg(all(feature = "builtin", feature = "import"))]
+impl ModuleApi for StaticModule {
+    fn functions(&self) -> Vec<FunctionInfo> {
+        StaticModule::functions(self)
+    }
+
+    fn has_fn(&self, name: &str) -> bool {
+        self.entries().any(|func| func.name == name)
+    }
+
+    fn call_with_borrowed(&self, name: &str, args: &[Borrowed<'_>]) -> Option<Owned> {
+        self.get_fn(name).map(|func| func.call_with_borrowed(args))
+    }
+
+    fn call(&self, name: &str, args: Vec<Owned>) -> Option<Owned> {
+        self.get_fn(name).map(|func| func.call(args))
+    }
+}
@@ -0,0 +1,175 @@
+use crate::convert::{ConvertError, FromValue, IntoArgs};
+use crate::error::{into_result, CallError};
+use crate::exported::{invoke_exported, ExportResult};
+use crate::object::{self, ObjectHeader, KIND_CALLBACK};
+use crate::value::*;
+use std::fmt;
+
+/// The key marking a generic map as a callback
+pub(crate) const CALLBACK_KEY: &str = "$dy_callback";
+
+/// The part of a callback every binary may read, possibly across DLLs
+///
+/// The closure follows it in memory; `call` and `release` of the binary which
+/// made the callback are the only code touching the closure.
+#[repr(C)]
+struct CallbackHeader {
+    object: ObjectHeader,
+    call: unsafe extern "C" fn(
+        header: *const CallbackHeader,
+        args: *const ValuePtr,
+        len: usize,
+    ) -> ValuePtr,
+}
+
+#[repr(C)]
+struct CallbackState<F> {
+    header: CallbackHeader,
+    f: F,
+}
+
+unsafe extern "C" fn call<F, R>(
+    header: *const CallbackHeader,
+    args: *const ValuePtr,
+    len: usize,
+) -> ValuePtr
+where
+    F: Fn(&[Borrowed<'_>]) -> R,
+    R: ExportResult,
+{
+    let state = &*(header as *const CallbackState<F>);
+    invoke_exported(args, len, |args| (state.f)(&args))
+}
+
+unsafe extern "C" fn release<F>(header: *const ObjectHeader) {
+    drop(Box::from_raw(header as *mut CallbackState<F>));
+}
+
+/// Indicates a Rust closure passed to an exported function, which calls it
+/// back with `dy` arguments
+///
+/// Exports take a `Callback` parameter to report progress or to request data
+/// from the host during a call; hosts pass one with `to_value`. Panics and
+/// errors of the closure are reported to the caller like those of an exported
+/// function. Crosses the DLL boundary as a generic map of the shape
+/// `{"$dy_callback": id}`, naming the callback in the object table the host
+/// shares with its DLLs; it is only found while a clone of the callback is
+/// alive, and never in another process.
+pub struct Callback {
+    header: *const CallbackHeader,
+}
+
+// the closure is `Send + Sync` and the reference count is atomic
+unsafe impl Send for Callback {}
+unsafe impl Sync for Callback {}
+
+impl Callback {
+    /// Makes a new callback calling a closure
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - the closure, returning any `ExportResult`
+    pub fn new<F, R>(f: F) -> Callback
+    where
+        F: Fn(&[Borrowed<'_>]) -> R + Send + Sync + 'static,
+        R: ExportResult,
+    {
+        let state = Box::into_raw(Box::new(CallbackState {
+            header: CallbackHeader {
+                object: ObjectHeader::new(release::<F>),
+                call: call::<F, R>,
+            },
+            f,
+        }));
+        unsafe { object::register(KIND_CALLBACK, state as *const ObjectHeader) };
+        Callback {
+            header: state as *const CallbackHeader,
+        }
+    }
+
+    fn header(&self) -> &CallbackHeader {
+        unsafe { &*self.header }
+    }
+
+    /// Makes the value passing the callback to an exported function
+    pub fn to_value(&self) -> Owned {
+        let id = self.header().object.id();
+        Value::new_map(vec![(CALLBACK_KEY, Value::new_int(id as i64))])
+    }
+
+    /// Invokes the closure
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments
+    pub fn call_with_borrowed(&self, args: &[Borrowed<'_>]) -> Owned {
+        let list_ptr: Vec<ValuePtr> = args.iter().map(|arg| arg.get_ptr()).collect();
+        let rtn = unsafe { (self.header().call)(self.header, list_ptr.as_ptr(), list_ptr.len()) };
+        unsafe { Owned::from_ptr(rtn) }
+    }
+
+    /// Invokes the closure and disposes arguments after the invocation
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments
+    pub fn call(&self, args: Vec<Owned>) -> Owned {
+        let borrowed: Vec<Borrowed<'_>> = args.iter().map(|arg| arg.borrow()).collect();
+        self.call_with_borrowed(&borrowed)
+    }
+
+    /// Invokes the closure and disposes arguments after the invocation,
+    /// reporting a panic or an error of the closure as `CallError`
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments
+    pub fn try_call(&self, args: Vec<Owned>) -> Result<Owned, CallError> {
+        into_result(self.call(args))
+    }
+
+    /// Invokes the closure with Rust types, converting the result back
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments, usually a tuple
+    pub fn call_typed<Args: IntoArgs, Ret: FromValue>(&self, args: Args) -> Result<Ret, CallError> {
+        let rtn = self.try_call(args.into_args())?;
+        Ret::from_value(&rtn).map_err(CallError::unexpected_return)
+    }
+}
+
+impl Clone for Callback {
+    fn clone(&self) -> Callback {
+        unsafe { object::retain(self.header as *const ObjectHeader) };
+        Callback {
+            header: self.header,
+        }
+    }
+}
+
+impl Drop for Callback {
+    fn drop(&mut self) {
+        unsafe { object::release(self.header as *const ObjectHeader) }
+    }
+}
+
+impl fmt::Debug for Callback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Callback")
+            .field("refs", &self.header().object.refs())
+            .finish()
+    }
+}
+
+impl FromValue for Callback {
+    fn from_value(val: &Value) -> Result<Self, ConvertError> {
+        let id = object::object_id(val, CALLBACK_KEY)
+            .ok_or_else(|| ConvertError::Invalid(String::from("expected a callback")))?;
+        let header = object::find(KIND_CALLBACK, id)
+            .ok_or_else(|| ConvertError::Invalid(String::from("the callback is not alive")))?;
+        Ok(Callback {
+            header: header as *const CallbackHeader,
+        })
+    }
+}
@@ -0,0 +1,108 @@
+use crate::convert::{ConvertError, FromValue};
+use crate::object::{self, ObjectHeader, KIND_CANCEL};
+use crate::value::*;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The key marking a generic map as a cancellation token
+pub(crate) const CANCEL_KEY: &str = "$dy_cancel";
+
+/// The state shared by the clones of a `CancelToken`, possibly across DLLs
+///
+/// It is freed by `release` of the binary which made it, so that it does not
+/// matter which side drops the last clone.
+#[repr(C)]
+struct CancelState {
+    header: ObjectHeader,
+    cancelled: AtomicBool,
+}
+
+unsafe extern "C" fn release(header: *const ObjectHeader) {
+    drop(Box::from_raw(header as *mut CancelState));
+}
+
+/// Indicates a flag an exported function polls to stop a call early
+///
+/// Exports opt into cancellation by taking a `CancelToken` as their last
+/// parameter; hosts pass one with `OwnedFunction::call_cancellable`.
+/// Crosses the DLL boundary as a generic map of the shape `{"$dy_cancel": id}`,
+/// naming the token in the object table the host shares with its DLLs; it is
+/// only found while a clone of the token is alive, and never in another process.
+pub struct CancelToken {
+    state: *const CancelState,
+}
+
+unsafe impl Send for CancelToken {}
+unsafe impl Sync for CancelToken {}
+
+impl CancelToken {
+    /// Makes a new token which is not cancelled
+    pub fn new() -> CancelToken {
+        let state = Box::into_raw(Box::new(CancelState {
+            header: ObjectHeader::new(release),
+            cancelled: AtomicBool::new(false),
+        }));
+        unsafe { object::register(KIND_CANCEL, state as *const ObjectHeader) };
+        CancelToken { state }
+    }
+
+    fn state(&self) -> &CancelState {
+        unsafe { &*self.state }
+    }
+
+    /// Requests every holder of the token to stop
+    pub fn cancel(&self) {
+        self.state().cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns whether the token has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.state().cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Makes the value passing the token to an exported function
+    pub fn to_value(&self) -> Owned {
+        let id = self.state().header.id();
+        Value::new_map(vec![(CANCEL_KEY, Value::new_int(id as i64))])
+    }
+}
+
+impl Default for CancelToken {
+    fn default() -> CancelToken {
+        CancelToken::new()
+    }
+}
+
+impl Clone for CancelToken {
+    fn clone(&self) -> CancelToken {
+        unsafe { object::retain(self.state as *const ObjectHeader) };
+        CancelToken { state: self.state }
+    }
+}
+
+impl Drop for CancelToken {
+    fn drop(&mut self) {
+        unsafe { object::release(self.state as *const ObjectHeader) }
+    }
+}
+
+impl fmt::Debug for CancelToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancelToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+impl FromValue for CancelToken {
+    fn from_value(val: &Value) -> Result<Self, ConvertError> {
+        let id = object::object_id(val, CANCEL_KEY)
+            .ok_or_else(|| ConvertError::Invalid(String::from("expected a cancellation token")))?;
+        let header = object::find(KIND_CANCEL, id).ok_or_else(|| {
+            ConvertError::Invalid(String::from("the cancellation token is not alive"))
+        })?;
+        Ok(CancelToken {
+            state: header as *const CancelState,
+        })
+    }
+}
@@ -0,0 +1,282 @@
+//! A lossless binary encoding of `dy` values
+//!
+//! Every value is a one-byte tag followed by its payload. Integers and
+//! floating point numbers are 8 bytes little-endian, floating point numbers
+//! being stored as their bit patterns; strings, arrays and maps are prefixed
+//! with their length as an 8-byte little-endian integer. A map entry is its
+//! key as a string followed by its value.
+//!
+//! Objects passed by id, e.g. callbacks, only mean something in the
+//! process which made them, so maps passing them are rejected when decoding.
+//!
+//! | tag | type       | payload                            |
+//! |-----|------------|------------------------------------|
+//! | 0   | `Null`     | none                               |
+//! | 1   | `Bool`     | 1 byte, 0 or 1                     |
+//! | 2   | `Int`      | 8 bytes                            |
+//! | 3   | `Float`    | 8 bytes                            |
+//! | 4   | `Str`      | length, bytes as stored, no NUL    |
+//! | 5   | `BoolArr`  | length, 1 byte per element         |
+//! | 6   | `Bytes`    | length, bytes                      |
+//! | 7   | `IntArr`   | length, 8 bytes per element        |
+//! | 8   | `FloatArr` | length, 8 bytes per element        |
+//! | 9   | `Arr`      | length, values                     |
+//! | 10  | `Map`      | length, key-value pairs            |
+
+use crate::object::is_object_key;
+use crate::value::*;
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+/// The deepest nesting of arrays and maps accepted by `read_value`
+pub const MAX_DEPTH: usize = 512;
+
+/// The number of elements reserved in advance, so that a corrupt length cannot exhaust memory
+const MAX_RESERVE: usize = 4096;
+
+fn tag_of(ty: Type) -> u8 {
+    match ty {
+        Type::Null => 0,
+        Type::Bool => 1,
+        Type::Int => 2,
+        Type::Float => 3,
+        Type::Str => 4,
+        Type::BoolArr => 5,
+        Type::Bytes => 6,
+        Type::IntArr => 7,
+        Type::FloatArr => 8,
+        Type::Arr => 9,
+        Type::Map => 10,
+    }
+}
+
+fn invalid_data(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+fn write_len<W: Write>(w: &mut W, len: usize) -> Result<()> {
+    w.write_all(&(len as u64).to_le_bytes())
+}
+
+fn write_str<W: Write>(w: &mut W, s: &[u8]) -> Result<()> {
+    write_len(w, s.len())?;
+    w.write_all(s)
+}
+
+/// Writes the encoding of a value
+///
+/// # Arguments
+///
+/// * `w` - the writer
+/// * `val` - the value to write
+pub fn write_value<W: Write>(w: &mut W, val: &Value) -> Result<()> {
+    w.write_all(&[tag_of(val.get_type())])?;
+    match val.as_type() {
+        As::Null(_) => Ok(()),
+        As::Bool(b) => w.write_all(&[b.get() as u8]),
+        As::Int(i) => w.write_all(&i.get().to_le_bytes()),
+        As::Float(f) => w.write_all(&f.get().to_bits().to_le_bytes()),
+        As::Str(s) => write_str(w, s.as_bytes()),
+        As::BoolArr(arr) => {
+            write_len(w, arr.len())?;
+            let data: Vec<u8> = (0..arr.len())
+                .filter_map(|idx| arr.at(idx))
+                .map(|b| b as u8)
+                .collect();
+            w.write_all(&data)
+        }
+        As::Bytes(bytes) => {
+            write_len(w, bytes.len())?;
+            w.write_all(bytes.data())
+        }
+        As::IntArr(arr) => {
+            write_len(w, arr.len())?;
+            for i in arr.data() {
+                w.write_all(&i.to_le_bytes())?;
+            }
+            Ok(())
+        }
+        As::FloatArr(arr) => {
+            write_len(w, arr.len())?;
+            for f in arr.data() {
+                w.write_all(&f.to_bits().to_le_bytes())?;
+            }
+            Ok(())
+        }
+        As::Arr(arr) => {
+            write_len(w, arr.len())?;
+            for elem in arr.iter() {
+                write_value(w, &elem)?;
+            }
+            Ok(())
+        }
+        As::Map(map) => {
+            write_len(w, map.size())?;
+            for pair in map.iter() {
+                write_str(w, pair.get_key().as_bytes())?;
+                write_value(w, pair.get_val())?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Encodes a value into a new buffer
+///
+/// # Arguments
+///
+/// * `val` - the value to encode
+pub fn encode(val: &Value) -> Vec<u8> {
+    let mut buf = Vec::new();
+    // writing into a `Vec` cannot fail
+    write_value(&mut buf, val).unwrap();
+    buf
+}
+
+fn read_u8<R: Read>(r: &mut R) -> Result<u8> {
+    let mut buf = [0u8; 1];
+    r.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u64<R: Read>(r: &mut R) -> Result<u64> {
+    let mut buf = [0u8; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_len<R: Read>(r: &mut R) -> Result<usize> {
+    let len = read_u64(r)?;
+    if len > usize::MAX as u64 {
+        return Err(invalid_data(format!("length {} is too large", len)));
+    }
+    Ok(len as usize)
+}
+
+fn read_bytes<R: Read>(r: &mut R, len: usize) -> Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(len.min(MAX_RESERVE));
+    r.take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() != len {
+        return Err(Error::new(
+            ErrorKind::UnexpectedEof,
+            "the value is truncated",
+        ));
+    }
+    Ok(buf)
+}
+
+fn read_str_bytes<R: Read>(r: &mut R) -> Result<Vec<u8>> {
+    let len = read_len(r)?;
+    let s = read_bytes(r, len)?;
+    if s.contains(&0) {
+        return Err(invalid_data(String::from(
+            "a string contains a NUL character",
+        )));
+    }
+    Ok(s)
+}
+
+fn read_str<R: Read>(r: &mut R) -> Result<String> {
+    String::from_utf8(read_str_bytes(r)?)
+        .map_err(|_| invalid_data(String::from("a key is not valid UTF-8")))
+}
+
+fn read_words<R: Read, T, F: Fn([u8; 8]) -> T>(r: &mut R, f: F) -> Result<Vec<T>> {
+    let len = read_len(r)?;
+    let mut rtn = Vec::with_capacity(len.min(MAX_RESERVE));
+    for _ in 0..len {
+        let mut buf = [0u8; 8];
+        r.read_exact(&mut buf)?;
+        rtn.push(f(buf));
+    }
+    Ok(rtn)
+}
+
+fn read_nested<R: Read>(r: &mut R, depth: usize) -> Result<Owned> {
+    let tag = read_u8(r)?;
+    if (tag == 9 || tag == 10) && depth >= MAX_DEPTH {
+        return Err(invalid_data(format!(
+            "values are nested deeper than {}",
+            MAX_DEPTH
+        )));
+    }
+    let rtn = match tag {
+        0 => Value::new_null(),
+        1 => match read_u8(r)? {
+            0 => Value::new_bool(false),
+            1 => Value::new_bool(true),
+            b => return Err(invalid_data(format!("{} is not a boolean", b))),
+        },
+        2 => Value::new_int(read_u64(r)? as i64),
+        3 => Value::new_float(f64::from_bits(read_u64(r)?)),
+        4 => Value::new_str_bytes(&read_str_bytes(r)?),
+        5 => {
+            let len = read_len(r)?;
+            let data = read_bytes(r, len)?;
+            if let Some(b) = data.iter().find(|b| **b > 1) {
+                return Err(invalid_data(format!("{} is not a boolean", b)));
+            }
+            let data: Vec<bool> = data.into_iter().map(|b| b == 1).collect();
+            Value::new_bool_arr(&data)
+        }
+        6 => {
+            let len = read_len(r)?;
+            Value::new_bytes(&read_bytes(r, len)?)
+        }
+        7 => Value::new_int_arr(&read_words(r, i64::from_le_bytes)?),
+        8 => Value::new_float_arr(&read_words(r, |buf| {
+            f64::from_bits(u64::from_le_bytes(buf))
+        })?),
+        9 => {
+            let len = read_len(r)?;
+            let mut elems = Vec::with_capacity(len.min(MAX_RESERVE));
+            for _ in 0..len {
+                elems.push(read_nested(r, depth + 1)?);
+            }
+            Value::new_arr(elems)
+        }
+        10 => {
+            let len = read_len(r)?;
+            let mut keys = Vec::with_capacity(len.min(MAX_RESERVE));
+            let mut vals = Vec::with_capacity(len.min(MAX_RESERVE));
+            for _ in 0..len {
+                let key = read_str(r)?;
+                if is_object_key(&key) {
+                    return Err(invalid_data(format!(
+                        "`{}` passes an object of another process",
+                        key
+                    )));
+                }
+                keys.push(key);
+                vals.push(read_nested(r, depth + 1)?);
+            }
+            Value::new_map(keys.iter().map(|key| key.as_str()).zip(vals).collect())
+        }
+        tag => return Err(invalid_data(format!("{} is not a type tag", tag))),
+    };
+    Ok(rtn)
+}
+
+/// Reads a value written by `write_value`
+///
+/// # Arguments
+///
+/// * `r` - the reader
+pub fn read_value<R: Read>(r: &mut R) -> Result<Owned> {
+    read_nested(r, 0)
+}
+
+/// Decodes a value, failing if the buffer holds anything after it
+///
+/// # Arguments
+///
+/// * `buf` - the encoding of the value
+pub fn decode(mut buf: &[u8]) -> Result<Owned> {
+    let rtn = read_value(&mut buf)?;
+    if !buf.is_empty() {
+        return Err(invalid_data(format!(
+            "{} bytes follow the value",
+            buf.len()
+        )));
+    }
+    Ok(rtn)
+}
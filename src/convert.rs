@@ -0,0 +1,400 @@
+use crate::value::*;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::hash::BuildHasher;
+
+/// Indicates a value could not be converted into a Rust type
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConvertError {
+    /// The value has another type
+    Type {
+        /// the type required by the Rust type
+        expected: Type,
+        /// the type of the value
+        found: Type,
+    },
+    /// The array has another length
+    Length {
+        /// the length required by the Rust type
+        expected: usize,
+        /// the length of the array
+        found: usize,
+    },
+    /// The value has the right type but cannot be represented by the Rust type
+    Invalid(String),
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConvertError::Type { expected, found } => {
+                write!(f, "expected {:?}, found {:?}", expected, found)
+            }
+            ConvertError::Length { expected, found } => {
+                write!(f, "expected {} elements, found {}", expected, found)
+            }
+            ConvertError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for ConvertError {}
+
+/// Checks the type of a value
+fn expect_type(val: &Value, expected: Type) -> Result<(), ConvertError> {
+    let found = val.get_type();
+    if found == expected {
+        Ok(())
+    } else {
+        Err(ConvertError::Type { expected, found })
+    }
+}
+
+/// Indicates a Rust type which can be read from a `dy` value
+pub trait FromValue: Sized {
+    /// Reads the value
+    ///
+    /// # Arguments
+    ///
+    /// * `val` - the value to read
+    fn from_value(val: &Value) -> Result<Self, ConvertError>;
+
+    /// Reads a typed array into `Vec<Self>`, if `Self` has a typed array counterpart
+    #[doc(hidden)]
+    fn vec_from_typed_arr(_val: &Value) -> Option<Result<Vec<Self>, ConvertError>> {
+        None
+    }
+}
+
+/// Indicates a Rust type which can be read from an argument of an exported
+/// function, possibly borrowing it for the duration of the call
+///
+/// Implemented for every `FromValue` type, `&str` and `Borrowed`.
+pub trait FromArg<'a>: Sized {
+    /// Reads the argument
+    ///
+    /// # Arguments
+    ///
+    /// * `val` - the argument to read
+    fn from_arg(val: &Borrowed<'a>) -> Result<Self, ConvertError>;
+}
+
+impl<'a, T: FromValue> FromArg<'a> for T {
+    fn from_arg(val: &Borrowed<'a>) -> Result<Self, ConvertError> {
+        T::from_value(val)
+    }
+}
+
+impl<'a> FromArg<'a> for &'a str {
+    fn from_arg(val: &Borrowed<'a>) -> Result<Self, ConvertError> {
+        expect_type(val, Type::Str)?;
+        std::str::from_utf8(val.str_bytes().unwrap())
+            .map_err(|_| ConvertError::Invalid(String::from("the string is not valid UTF-8")))
+    }
+}
+
+impl<'a> FromArg<'a> for Borrowed<'a> {
+    fn from_arg(val: &Borrowed<'a>) -> Result<Self, ConvertError> {
+        Ok(unsafe { Borrowed::from_ptr(val.get_ptr()) })
+    }
+}
+
+/// Indicates a Rust type which can be made into a `dy` value
+pub trait IntoValue {
+    /// Makes the value
+    fn into_value(self) -> Owned;
+
+    /// Makes `Vec<Self>` into an array, a typed array if `Self` has a typed array counterpart
+    #[doc(hidden)]
+    fn vec_into_value(v: Vec<Self>) -> Owned
+    where
+        Self: Sized,
+    {
+        Value::new_arr(v.into_iter().map(|elem| elem.into_value()).collect())
+    }
+}
+
+impl FromValue for Owned {
+    fn from_value(val: &Value) -> Result<Self, ConvertError> {
+        Ok(val.copy())
+    }
+}
+
+impl IntoValue for Owned {
+    fn into_value(self) -> Owned {
+        self
+    }
+}
+
+impl FromValue for SharedValue {
+    fn from_value(val: &Value) -> Result<Self, ConvertError> {
+        Ok(SharedValue::new(val.copy()))
+    }
+}
+
+impl IntoValue for SharedValue {
+    fn into_value(self) -> Owned {
+        self.into_owned()
+    }
+}
+
+impl FromValue for () {
+    fn from_value(val: &Value) -> Result<Self, ConvertError> {
+        expect_type(val, Type::Null)
+    }
+}
+
+impl IntoValue for () {
+    fn into_value(self) -> Owned {
+        Value::new_null()
+    }
+}
+
+macro_rules! impl_primitive_convert {
+    ($($ty:ty: $ty_name:ident, $as:ident, $new:ident; $as_arr:ident, $new_arr:ident);+ $(;)?) => {
+        $(
+            impl FromValue for $ty {
+                fn from_value(val: &Value) -> Result<Self, ConvertError> {
+                    expect_type(val, Type::$ty_name)?;
+                    Ok(val.$as().unwrap().get())
+                }
+
+                fn vec_from_typed_arr(val: &Value) -> Option<Result<Vec<Self>, ConvertError>> {
+                    val.$as_arr()
+                        .map(|arr| Ok((0..arr.len()).filter_map(|idx| arr.at(idx)).collect()))
+                }
+            }
+
+            impl IntoValue for $ty {
+                fn into_value(self) -> Owned {
+                    Value::$new(self)
+                }
+
+                fn vec_into_value(v: Vec<Self>) -> Owned {
+                    Value::$new_arr(&v)
+                }
+            }
+        )+
+    };
+}
+
+impl_primitive_convert! {
+    bool: Bool, as_bool, new_bool; as_bool_arr, new_bool_arr;
+    i64: Int, as_int, new_int; as_int_arr, new_int_arr;
+    f64: Float, as_float, new_float; as_float_arr, new_float_arr;
+}
+
+macro_rules! impl_int_convert {
+    ($($ty:ty),+ $(,)?) => {
+        $(
+            impl FromValue for $ty {
+                fn from_value(val: &Value) -> Result<Self, ConvertError> {
+                    let i = i64::from_value(val)?;
+                    <$ty>::try_from(i).map_err(|_| {
+                        let ty = stringify!($ty);
+                        ConvertError::Invalid(format!("{} is out of the range of {}", i, ty))
+                    })
+                }
+            }
+
+            impl IntoValue for $ty {
+                fn into_value(self) -> Owned {
+                    Value::new_int(self as i64)
+                }
+            }
+        )+
+    };
+}
+
+impl_int_convert!(i32, u32, usize);
+
+impl FromValue for u8 {
+    fn from_value(val: &Value) -> Result<Self, ConvertError> {
+        let i = i64::from_value(val)?;
+        u8::try_from(i)
+            .map_err(|_| ConvertError::Invalid(format!("{} is out of the range of u8", i)))
+    }
+
+    fn vec_from_typed_arr(val: &Value) -> Option<Result<Vec<Self>, ConvertError>> {
+        val.as_bytes().map(|bytes| Ok(bytes.data().to_vec()))
+    }
+}
+
+impl IntoValue for u8 {
+    fn into_value(self) -> Owned {
+        Value::new_int(i64::from(self))
+    }
+
+    fn vec_into_value(v: Vec<Self>) -> Owned {
+        Value::new_bytes(&v)
+    }
+}
+
+impl FromValue for String {
+    fn from_value(val: &Value) -> Result<Self, ConvertError> {
+        expect_type(val, Type::Str)?;
+        Ok(val.as_str().unwrap().get())
+    }
+}
+
+impl IntoValue for String {
+    fn into_value(self) -> Owned {
+        Value::new_str(&self)
+    }
+}
+
+impl IntoValue for &str {
+    fn into_value(self) -> Owned {
+        Value::new_str(self)
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(val: &Value) -> Result<Self, ConvertError> {
+        if val.is_null() {
+            Ok(None)
+        } else {
+            T::from_value(val).map(Some)
+        }
+    }
+}
+
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self) -> Owned {
+        match self {
+            Some(v) => v.into_value(),
+            None => Value::new_null(),
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(val: &Value) -> Result<Self, ConvertError> {
+        if let Some(rtn) = T::vec_from_typed_arr(val) {
+            return rtn;
+        }
+        expect_type(val, Type::Arr)?;
+        val.as_arr()
+            .unwrap()
+            .iter()
+            .map(|elem| T::from_value(&elem))
+            .collect()
+    }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value(self) -> Owned {
+        T::vec_into_value(self)
+    }
+}
+
+/// Reads the entries of a generic map
+fn map_entries<T: FromValue>(val: &Value) -> Result<Vec<(String, T)>, ConvertError> {
+    expect_type(val, Type::Map)?;
+    val.as_map()
+        .unwrap()
+        .iter()
+        .map(|pair| Ok((String::from(pair.get_key()), T::from_value(pair.get_val())?)))
+        .collect()
+}
+
+/// Makes a generic map from owned keys
+fn new_map_from(entries: Vec<(String, Owned)>) -> Owned {
+    let (keys, vals): (Vec<String>, Vec<Owned>) = entries.into_iter().unzip();
+    Value::new_map(keys.iter().map(|key| key.as_str()).zip(vals).collect())
+}
+
+impl<T: FromValue, S: BuildHasher + Default> FromValue for HashMap<String, T, S> {
+    fn from_value(val: &Value) -> Result<Self, ConvertError> {
+        Ok(map_entries(val)?.into_iter().collect())
+    }
+}
+
+impl<T: IntoValue, S: BuildHasher> IntoValue for HashMap<String, T, S> {
+    fn into_value(self) -> Owned {
+        new_map_from(self.into_iter().map(|(k, v)| (k, v.into_value())).collect())
+    }
+}
+
+impl<T: FromValue> FromValue for BTreeMap<String, T> {
+    fn from_value(val: &Value) -> Result<Self, ConvertError> {
+        Ok(map_entries(val)?.into_iter().collect())
+    }
+}
+
+impl<T: IntoValue> IntoValue for BTreeMap<String, T> {
+    fn into_value(self) -> Owned {
+        new_map_from(self.into_iter().map(|(k, v)| (k, v.into_value())).collect())
+    }
+}
+
+macro_rules! impl_tuple_convert {
+    ($($len:literal => ($($name:ident: $idx:tt),+));+ $(;)?) => {
+        $(
+            impl<$($name: FromValue),+> FromValue for ($($name,)+) {
+                fn from_value(val: &Value) -> Result<Self, ConvertError> {
+                    expect_type(val, Type::Arr)?;
+                    let arr = val.as_arr().unwrap();
+                    if arr.len() != $len {
+                        return Err(ConvertError::Length {
+                            expected: $len,
+                            found: arr.len(),
+                        });
+                    }
+                    Ok(($($name::from_value(&arr.at($idx).unwrap())?,)+))
+                }
+            }
+
+            impl<$($name: IntoValue),+> IntoValue for ($($name,)+) {
+                fn into_value(self) -> Owned {
+                    Value::new_arr(vec![$(self.$idx.into_value()),+])
+                }
+            }
+        )+
+    };
+}
+
+impl_tuple_convert! {
+    1 => (A: 0);
+    2 => (A: 0, B: 1);
+    3 => (A: 0, B: 1, C: 2);
+    4 => (A: 0, B: 1, C: 2, D: 3);
+    5 => (A: 0, B: 1, C: 2, D: 3, E: 4);
+    6 => (A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+}
+
+/// Indicates the arguments of a function, usually a tuple of `IntoValue` types
+pub trait IntoArgs {
+    /// Makes the values of the arguments
+    fn into_args(self) -> Vec<Owned>;
+}
+
+impl IntoArgs for Vec<Owned> {
+    fn into_args(self) -> Vec<Owned> {
+        self
+    }
+}
+
+macro_rules! impl_into_args {
+    ($(($($name:ident: $idx:tt),*));+ $(;)?) => {
+        $(
+            impl<$($name: IntoValue),*> IntoArgs for ($($name,)*) {
+                fn into_args(self) -> Vec<Owned> {
+                    vec![$(self.$idx.into_value()),*]
+                }
+            }
+        )+
+    };
+}
+
+impl_into_args! {
+    ();
+    (A: 0);
+    (A: 0, B: 1);
+    (A: 0, B: 1, C: 2);
+    (A: 0, B: 1, C: 2, D: 3);
+    (A: 0, B: 1, C: 2, D: 3, E: 4);
+    (A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+}
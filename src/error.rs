@@ -0,0 +1,218 @@
+use crate::convert::ConvertError;
+use crate::value::*;
+use std::error::Error;
+use std::fmt;
+use std::panic::Location;
+use std::time::Duration;
+
+/// The key marking a generic map as an error value
+const ERROR_KEY: &str = "$dy_error";
+
+/// Indicates an exported function failed instead of returning a value
+///
+/// Crosses the DLL boundary as a generic map of the shape
+/// `{"$dy_error": "panic" | "error" | "argument" | "timeout", "message": ..., "location": ...}`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CallError {
+    /// The function panicked
+    Panic {
+        /// the panic message
+        message: String,
+        /// the source location of the panic, e.g. `src/lib.rs:10:5`
+        location: Option<String>,
+    },
+    /// The function returned an error
+    Error {
+        /// the error message
+        message: String,
+        /// the source location the error was made at
+        location: Option<String>,
+    },
+    /// The arguments could not be converted into the parameters of the function
+    Argument {
+        /// the error message, e.g. naming the parameter
+        message: String,
+    },
+    /// The function returned a value which could not be converted into the expected type
+    Return {
+        /// the reason of the failed conversion
+        error: ConvertError,
+        /// the error message
+        message: String,
+    },
+    /// The function did not return before the deadline
+    Timeout {
+        /// the time the function was given
+        timeout: Duration,
+        /// the error message
+        message: String,
+    },
+}
+
+impl CallError {
+    /// Makes a new error recording the location of the caller
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - the error message
+    #[track_caller]
+    pub fn new(message: &str) -> CallError {
+        CallError::Error {
+            message: String::from(message),
+            location: Some(Location::caller().to_string()),
+        }
+    }
+
+    /// Makes the error of a return value which could not be converted
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - the reason of the failed conversion
+    pub fn unexpected_return(error: ConvertError) -> CallError {
+        CallError::Return {
+            message: error.to_string(),
+            error,
+        }
+    }
+
+    /// Makes the error of a function which did not return in time
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - the time the function was given
+    pub fn timeout(timeout: Duration) -> CallError {
+        CallError::Timeout {
+            timeout,
+            message: format!("timed out after {:?}", timeout),
+        }
+    }
+
+    /// Returns the message of the error
+    pub fn message(&self) -> &str {
+        match self {
+            CallError::Panic { message, .. }
+            | CallError::Error { message, .. }
+            | CallError::Argument { message }
+            | CallError::Return { message, .. }
+            | CallError::Timeout { message, .. } => message,
+        }
+    }
+
+    /// Returns the source location of the error, if known
+    pub fn location(&self) -> Option<&str> {
+        match self {
+            CallError::Panic { location, .. } | CallError::Error { location, .. } => {
+                location.as_deref()
+            }
+            CallError::Argument { .. } | CallError::Return { .. } | CallError::Timeout { .. } => {
+                None
+            }
+        }
+    }
+
+    /// Makes the error value representing this error
+    pub fn to_value(&self) -> Owned {
+        let kind = match self {
+            CallError::Panic { .. } => "panic",
+            CallError::Error { .. } | CallError::Return { .. } => "error",
+            CallError::Argument { .. } => "argument",
+            CallError::Timeout { .. } => "timeout",
+        };
+        let mut entries = vec![
+            (ERROR_KEY, Value::new_str(kind)),
+            ("message", Value::new_str(self.message())),
+        ];
+        if let Some(location) = self.location() {
+            entries.push(("location", Value::new_str(location)));
+        }
+        if let CallError::Timeout { timeout, .. } = self {
+            entries.push(("seconds", Value::new_float(timeout.as_secs_f64())));
+        }
+        Value::new_map(entries)
+    }
+
+    /// Reads an error value, returning `None` if the value is not an error value
+    ///
+    /// # Arguments
+    ///
+    /// * `val` - the value to read
+    pub fn from_value(val: &Value) -> Option<CallError> {
+        let map = val.as_map()?;
+        let kind = map.at(ERROR_KEY)?.get_val().as_str()?.get();
+        let get_str = |key: &str| {
+            map.at(key)
+                .and_then(|pair| pair.get_val().as_str().map(|s| s.get()))
+        };
+        let message = get_str("message").unwrap_or_default();
+        let location = get_str("location");
+        match kind.as_str() {
+            "panic" => Some(CallError::Panic { message, location }),
+            "argument" => Some(CallError::Argument { message }),
+            "timeout" => {
+                let seconds = map
+                    .at("seconds")
+                    .and_then(|pair| pair.get_val().as_float().map(|f| f.get()))
+                    .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+                    .unwrap_or_default();
+                Some(CallError::timeout(Duration::from_secs_f64(seconds)))
+            }
+            _ => Some(CallError::Error { message, location }),
+        }
+    }
+}
+
+/// Separates error values from ordinary return values
+pub(crate) fn into_result(rtn: Owned) -> Result<Owned, CallError> {
+    match CallError::from_value(&rtn) {
+        Some(err) => Err(err),
+        None => Ok(rtn),
+    }
+}
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallError::Panic { message, location } => match location {
+                Some(location) => write!(f, "panicked at {}: {}", location, message),
+                None => write!(f, "panicked: {}", message),
+            },
+            CallError::Error { message, location } => match location {
+                Some(location) => write!(f, "{} (at {})", message, location),
+                None => write!(f, "{}", message),
+            },
+            CallError::Argument { message } | CallError::Timeout { message, .. } => {
+                write!(f, "{}", message)
+            }
+            CallError::Return { message, .. } => write!(f, "unexpected return value: {}", message),
+        }
+    }
+}
+
+impl Error for CallError {}
+
+impl From<String> for CallError {
+    fn from(message: String) -> CallError {
+        CallError::Error {
+            message,
+            location: None,
+        }
+    }
+}
+
+impl From<&str> for CallError {
+    fn from(message: &str) -> CallError {
+        CallError::from(String::from(message))
+    }
+}
+
+impl From<Box<dyn Error + Send + Sync>> for CallError {
+    fn from(err: Box<dyn Error + Send + Sync>) -> CallError {
+        CallError::from(err.to_string())
+    }
+}
+
+impl From<ConvertError> for CallError {
+    fn from(err: ConvertError) -> CallError {
+        CallError::from(err.to_string())
+    }
+}
@@ -0,0 +1,310 @@
+use crate::convert::{FromArg, IntoValue};
+use crate::error::CallError;
+use crate::value::*;
+use std::any::Any;
+use std::cell::RefCell;
+use std::panic::{catch_unwind, set_hook, take_hook, AssertUnwindSafe};
+use std::slice::from_raw_parts;
+use std::sync::Once;
+
+/// The signature of functions exported using `dy`
+pub type RawFunction = unsafe extern "C" fn(args: *const ValuePtr, len: usize) -> ValuePtr;
+
+/// Indicates a type an exported function may return
+pub trait ExportResult {
+    /// Converts the returned value into a value or an error
+    fn into_result(self) -> Result<Owned, CallError>;
+}
+
+impl<T: IntoValue> ExportResult for T {
+    fn into_result(self) -> Result<Owned, CallError> {
+        Ok(self.into_value())
+    }
+}
+
+impl<T: IntoValue, E: Into<CallError>> ExportResult for Result<T, E> {
+    fn into_result(self) -> Result<Owned, CallError> {
+        self.map(|val| val.into_value()).map_err(|err| err.into())
+    }
+}
+
+/// Indicates the parameters of an exported function, as a tuple
+///
+/// `(Vec<Borrowed<'_>>,)` takes any number of arguments as they are.
+pub trait FromArgs<'a>: Sized {
+    /// The number of arguments, `None` if any number is accepted
+    const ARITY: Option<usize>;
+
+    /// Checks the number of arguments and converts them
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments passed by the host
+    /// * `names` - the names of the parameters, used in errors
+    fn from_args(args: Vec<Borrowed<'a>>, names: &[&str]) -> Result<Self, CallError>;
+}
+
+impl<'a> FromArgs<'a> for (Vec<Borrowed<'a>>,) {
+    const ARITY: Option<usize> = None;
+
+    fn from_args(args: Vec<Borrowed<'a>>, _names: &[&str]) -> Result<Self, CallError> {
+        Ok((args,))
+    }
+}
+
+/// Converts the argument at the given index
+fn from_arg<'a, T: FromArg<'a>>(
+    args: &[Borrowed<'a>],
+    names: &[&str],
+    idx: usize,
+) -> Result<T, CallError> {
+    T::from_arg(&args[idx]).map_err(|err| match names.get(idx) {
+        Some(name) => CallError::Argument {
+            message: format!("argument `{}`: {}", name, err),
+        },
+        None => CallError::Argument {
+            message: format!("argument {}: {}", idx, err),
+        },
+    })
+}
+
+macro_rules! impl_from_args {
+    ($($len:literal => ($($name:ident: $idx:tt),*));+ $(;)?) => {
+        $(
+            impl<'a, $($name: FromArg<'a>),*> FromArgs<'a> for ($($name,)*) {
+                const ARITY: Option<usize> = Some($len);
+
+                #[allow(unused_variables)]
+                fn from_args(args: Vec<Borrowed<'a>>, names: &[&str]) -> Result<Self, CallError> {
+                    if args.len() != $len {
+                        return Err(CallError::Argument {
+                            message: format!("expected {} arguments, found {}", $len, args.len()),
+                        });
+                    }
+                    Ok(($(from_arg::<$name>(&args, names, $idx)?,)*))
+                }
+            }
+        )+
+    };
+}
+
+impl_from_args! {
+    0 => ();
+    1 => (A: 0);
+    2 => (A: 0, B: 1);
+    3 => (A: 0, B: 1, C: 2);
+    4 => (A: 0, B: 1, C: 2, D: 3);
+    5 => (A: 0, B: 1, C: 2, D: 3, E: 4);
+    6 => (A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+}
+
+thread_local! {
+    static PANIC_LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+static PANIC_HOOK: Once = Once::new();
+
+/// Chains a panic hook recording the location of the last panic of each thread
+fn install_panic_hook() {
+    PANIC_HOOK.call_once(|| {
+        let prev = take_hook();
+        set_hook(Box::new(move |info| {
+            let location = info.location().map(|location| location.to_string());
+            PANIC_LOCATION.with(|last| *last.borrow_mut() = location);
+            prev(info);
+        }));
+    });
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        String::from(*message)
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        String::from("Box<dyn Any>")
+    }
+}
+
+/// Runs the body of an exported function, so that it never unwinds across `extern "C"`
+///
+/// Panics and returned errors become error values, which the host reads with
+/// `Function::try_call`. This is what `#[export]` and `exported!` expand to.
+///
+/// # Safety
+///
+/// `args` must point to `len` valid values, which outlive the call.
+///
+/// # Arguments
+///
+/// * `args` - the arguments passed by the host
+/// * `len` - the number of arguments
+/// * `f` - the body of the function
+pub unsafe fn invoke_exported<F, R>(args: *const ValuePtr, len: usize, f: F) -> ValuePtr
+where
+    F: FnOnce(Vec<Borrowed<'_>>) -> R,
+    R: ExportResult,
+{
+    install_panic_hook();
+
+    let args: Vec<Borrowed<'_>> = if len == 0 {
+        Vec::new()
+    } else {
+        from_raw_parts(args, len)
+            .iter()
+            .map(|ptr| Borrowed::from_ptr(*ptr))
+            .collect()
+    };
+
+    let rtn = match catch_unwind(AssertUnwindSafe(|| f(args).into_result())) {
+        Ok(Ok(val)) => val,
+        Ok(Err(err)) => err.to_value(),
+        Err(payload) => CallError::Panic {
+            message: panic_message(payload.as_ref()),
+            location: PANIC_LOCATION.with(|last| last.borrow_mut().take()),
+        }
+        .to_value(),
+    };
+    rtn.into_ptr()
+}
+
+/// Exports functions like `#[export]`, without the `export` feature
+///
+/// Parameters may be of any `FromArg` type, e.g. a `FromValue` type or `&str`,
+/// borrowed for the call; the number and the types of the
+/// arguments are checked before the body runs. A single `Vec<Borrowed<'_>>`
+/// parameter takes the arguments as they are instead. The function returns
+/// any `ExportResult`, e.g. `f64`, `Owned` or `Result<Vec<i64>, CallError>`.
+/// Panics and errors are reported to the host. Doc comments are put in the
+/// manifest of the DLL.
+///
+/// ```ignore
+/// dy::exported! {
+///     /// Returns the square root of a non-negative number
+///     pub fn checked_sqrt(x: f64) -> Result<f64, CallError> {
+///         if x >= 0.0 {
+///             Ok(x.sqrt())
+///         } else {
+///             Err(CallError::new("expected a non-negative number"))
+///         }
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! exported {
+    (
+        $(
+            $(#[$($attr:tt)*])*
+            pub fn $name:ident ( $($param:ident : $ty:ty),* $(,)? ) $(-> $ret:ty)? $body:block
+        )*
+    ) => {
+        $(
+            $crate::__export_symbol! {
+                #[no_mangle]
+                $(#[$($attr)*])*
+                pub unsafe extern "C" fn $name(
+                    args: *const $crate::ValuePtr,
+                    len: usize,
+                ) -> $crate::ValuePtr {
+                    fn body($($param: $ty),*) $(-> $ret)? $body
+                    $crate::invoke_exported(args, len, |args| {
+                        let ($($param,)*) = <($($ty,)*) as $crate::FromArgs<'_>>::from_args(
+                            args,
+                            &[$(stringify!($param)),*],
+                        )?;
+                        $crate::ExportResult::into_result(body($($param),*))
+                    })
+                }
+            }
+
+            $crate::__register_builtin!(
+                $name,
+                stringify!($name),
+                <($($ty,)*) as $crate::FromArgs<'static>>::ARITY,
+                stringify!(($($param: $ty),*) $(-> $ret)?),
+                $crate::__doc_of!($([$($attr)*])*),
+            );
+        )*
+    };
+}
+
+/// Registers `extern "C"` functions written by hand, so that `StaticModule`
+/// finds them when the crate is linked statically
+///
+/// Functions exported with `#[export]` or `exported!` are registered already;
+/// registering them again makes `StaticModule::all` fail. Does nothing unless
+/// the `builtin` or `export` feature is enabled.
+///
+/// ```ignore
+/// unsafe extern "C" fn count(args: *const ValuePtr, len: usize) -> ValuePtr {
+///     invoke_exported(args, len, |args| args.len() as i64)
+/// }
+///
+/// dy::register!(count);
+/// ```
+#[macro_export]
+macro_rules! register {
+    ($($name:ident),* $(,)?) => {
+        $($crate::__register_builtin!($name);)*
+    };
+}
+
+/// Concatenates the doc comments among attributes, one line each
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __doc_of {
+    () => { "" };
+    ([doc = $doc:literal] $($rest:tt)*) => {
+        concat!($doc, "\n", $crate::__doc_of!($($rest)*))
+    };
+    ([$($attr:tt)*] $($rest:tt)*) => { $crate::__doc_of!($($rest)*) };
+}
+
+/// Exports an `extern "C"` function under the symbol given by the attribute
+///
+/// With the `builtin` feature the function is linked statically and found
+/// through `StaticModule` only, so that crates exporting functions of the same
+/// name can be linked into one binary.
+#[cfg(not(feature = "builtin"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __export_symbol {
+    (#[$symbol:meta] $item:item) => {
+        #[$symbol]
+        $item
+    };
+}
+
+#[cfg(feature = "builtin")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __export_symbol {
+    (#[$symbol:meta] $item:item) => {
+        #[allow(dead_code)]
+        $item
+    };
+}
+
+#[cfg(any(feature = "builtin", feature = "export"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __register_builtin {
+    ($raw:path) => {
+        $crate::inventory::submit! {
+            $crate::StaticFunction::new(env!("CARGO_CRATE_NAME"), stringify!($raw), $raw)
+        }
+    };
+    ($raw:path, $name:expr, $arity:expr, $signature:expr, $doc:expr $(,)?) => {
+        $crate::inventory::submit! {
+            $crate::StaticFunction::new(env!("CARGO_CRATE_NAME"), $name, $raw)
+                .with_info($arity, $signature, $doc)
+        }
+    };
+}
+
+#[cfg(not(any(feature = "builtin", feature = "export")))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __register_builtin {
+    ($($tt:tt)*) => {};
+}
@@ -0,0 +1,199 @@
+use crate::convert::{ConvertError, FromValue, IntoValue};
+use crate::object::{self, ObjectHeader, KIND_HANDLE, KIND_HANDLE_TICKET};
+use crate::value::*;
+use std::fmt;
+use std::mem::size_of;
+use std::slice::from_raw_parts;
+use std::str::from_utf8_unchecked;
+
+/// The key marking a generic map as a handle
+pub(crate) const HANDLE_KEY: &str = "$dy_handle";
+
+/// The key of the ticket of the reference a handle moved into a value
+pub(crate) const OWNED_KEY: &str = "$dy_handle_owned";
+
+/// Indicates a type which may be put in a `Handle`
+///
+/// # Safety
+///
+/// `TAG` must name this type only, in every binary exchanging handles, e.g.
+/// `"my_plugin::Parser/1"`; `Handle::get` trusts a matching tag.
+pub unsafe trait HandleType: Send + Sync + 'static {
+    /// The tag checked by `Handle::get`
+    const TAG: &'static str;
+}
+
+/// The part of a handle every binary may read, possibly across DLLs
+///
+/// The object follows it in memory; `release` of the binary which made the
+/// handle is the only code dropping the object.
+#[repr(C)]
+struct HandleHeader {
+    /// counts the `Handle` instances and the values made by `into_value` not read yet
+    object: ObjectHeader,
+    tag: *const u8,
+    tag_len: usize,
+    size: usize,
+}
+
+#[repr(C)]
+struct HandleState<T> {
+    header: HandleHeader,
+    val: T,
+}
+
+unsafe extern "C" fn release<T>(header: *const ObjectHeader) {
+    drop(Box::from_raw(header as *mut HandleState<T>));
+}
+
+/// Indicates a native object, e.g. a parser or a connection, passed between
+/// an exported function and the host by reference
+///
+/// A plugin returns a handle to its host, which passes it back on later calls;
+/// the object is dropped by the binary which made it once every clone is gone.
+/// Crosses the DLL boundary as a generic map of the shape `{"$dy_handle": id,
+/// "tag": ...}`, naming the handle in the object table the host shares with its
+/// DLLs, so it cannot be sent to another process. A handle keeps the DLL
+/// which made it loaded, even once every module loading the DLL is dropped.
+///
+/// Passing a handle with `to_value` lends it for the duration of a call; the
+/// value only finds the object while a clone of the handle is alive.
+/// Returning it with `into_value` moves a reference into a one-time ticket,
+/// recorded in the value as `"$dy_handle_owned"` and taken over by the first
+/// `from_value` reading it; later reads take references of their own. The
+/// object leaks if the value is dropped without being read.
+pub struct Handle {
+    header: *const HandleHeader,
+}
+
+// the object is `Send + Sync` and the reference counts are atomic
+unsafe impl Send for Handle {}
+unsafe impl Sync for Handle {}
+
+impl Handle {
+    /// Makes a new handle owning an object
+    ///
+    /// # Arguments
+    ///
+    /// * `val` - the object
+    pub fn new<T: HandleType>(val: T) -> Handle {
+        let state = Box::into_raw(Box::new(HandleState {
+            header: HandleHeader {
+                object: ObjectHeader::new(release::<T>),
+                tag: T::TAG.as_ptr(),
+                tag_len: T::TAG.len(),
+                size: size_of::<T>(),
+            },
+            val,
+        }));
+        unsafe { object::register(KIND_HANDLE, state as *const ObjectHeader) };
+        Handle {
+            header: state as *const HandleHeader,
+        }
+    }
+
+    fn header(&self) -> &HandleHeader {
+        unsafe { &*self.header }
+    }
+
+    /// Returns the tag of the type of the object
+    pub fn tag(&self) -> &str {
+        let header = self.header();
+        unsafe { from_utf8_unchecked(from_raw_parts(header.tag, header.tag_len)) }
+    }
+
+    /// Returns `true` if the object is of the given type
+    pub fn is<T: HandleType>(&self) -> bool {
+        self.tag() == T::TAG && self.header().size == size_of::<T>()
+    }
+
+    /// Borrows the object, checking its type by its tag
+    pub fn get<T: HandleType>(&self) -> Result<&T, ConvertError> {
+        if !self.is::<T>() {
+            return Err(ConvertError::Invalid(format!(
+                "expected a handle of `{}`, found `{}`",
+                T::TAG,
+                self.tag()
+            )));
+        }
+        Ok(unsafe { &(*(self.header as *const HandleState<T>)).val })
+    }
+
+    /// Makes the value lending the handle to an exported function for a call
+    pub fn to_value(&self) -> Owned {
+        Value::new_map(vec![
+            (HANDLE_KEY, Value::new_int(self.header().object.id() as i64)),
+            ("tag", Value::new_str(self.tag())),
+        ])
+    }
+
+    /// Returns `true` if both instances refer to the same object
+    ///
+    /// # Arguments
+    ///
+    /// * `a` - the first instance
+    /// * `b` - the second instance
+    pub fn ptr_eq(a: &Handle, b: &Handle) -> bool {
+        a.header == b.header
+    }
+}
+
+impl Clone for Handle {
+    fn clone(&self) -> Handle {
+        unsafe { object::retain(self.header as *const ObjectHeader) };
+        Handle {
+            header: self.header,
+        }
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        unsafe { object::release(self.header as *const ObjectHeader) }
+    }
+}
+
+impl fmt::Debug for Handle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handle").field("tag", &self.tag()).finish()
+    }
+}
+
+impl IntoValue for Handle {
+    fn into_value(self) -> Owned {
+        let id = Value::new_int(self.header().object.id() as i64);
+        let tag = Value::new_str(self.tag());
+        let header = self.header as *const ObjectHeader;
+        // the reference of `self` now belongs to the ticket
+        std::mem::forget(self);
+        let ticket = unsafe { object::insert_entry(KIND_HANDLE_TICKET, header) };
+        Value::new_map(vec![
+            (HANDLE_KEY, id),
+            ("tag", tag),
+            (OWNED_KEY, Value::new_int(ticket as i64)),
+        ])
+    }
+}
+
+impl FromValue for Handle {
+    fn from_value(val: &Value) -> Result<Self, ConvertError> {
+        let id = object::object_id(val, HANDLE_KEY)
+            .ok_or_else(|| ConvertError::Invalid(String::from("expected a handle")))?;
+        let ticket = object::object_id(val, OWNED_KEY)
+            .and_then(|ticket| object::take_entry(KIND_HANDLE_TICKET, ticket));
+        if let Some(header) = ticket {
+            let rtn = Handle {
+                header: header as *const HandleHeader,
+            };
+            if rtn.header().object.id() == id {
+                return Ok(rtn);
+            }
+            // the ticket of another handle; its reference is ours to drop
+        }
+        let header = object::find(KIND_HANDLE, id)
+            .ok_or_else(|| ConvertError::Invalid(String::from("the handle is not alive")))?;
+        Ok(Handle {
+            header: header as *const HandleHeader,
+        })
+    }
+}
@@ -0,0 +1,218 @@
+use crate::error::CallError;
+use crate::value::*;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Observes every call of the functions of a `Module`
+///
+/// Hooks are added with `Module::add_hook` and run on the calling thread, in
+/// the order they were added.
+pub trait CallHook: Send + Sync {
+    /// Runs before the function is called
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the function
+    /// * `args` - the arguments
+    fn before(&self, _name: &str, _args: &[Borrowed<'_>]) {}
+
+    /// Runs after the function has returned
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the function
+    /// * `args` - the arguments
+    /// * `rtn` - the returned value, possibly an error value
+    /// * `elapsed` - the time the function took
+    fn after(&self, _name: &str, _args: &[Borrowed<'_>], _rtn: &Value, _elapsed: Duration) {}
+}
+
+impl<H: CallHook + ?Sized> CallHook for Arc<H> {
+    fn before(&self, name: &str, args: &[Borrowed<'_>]) {
+        (**self).before(name, args)
+    }
+
+    fn after(&self, name: &str, args: &[Borrowed<'_>], rtn: &Value, elapsed: Duration) {
+        (**self).after(name, args, rtn, elapsed)
+    }
+}
+
+/// The upper bounds of the buckets of `CallStats::histogram`; the last bucket has none
+pub const LATENCY_BOUNDS: [Duration; 7] = [
+    Duration::from_micros(10),
+    Duration::from_micros(100),
+    Duration::from_millis(1),
+    Duration::from_millis(10),
+    Duration::from_millis(100),
+    Duration::from_secs(1),
+    Duration::from_secs(10),
+];
+
+/// Indicates the statistics `MetricsHook` collects for one function
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CallStats {
+    /// the number of calls
+    pub calls: u64,
+    /// the number of calls which returned an error value
+    pub errors: u64,
+    /// the time spent in all calls
+    pub total: Duration,
+    /// the time spent in the slowest call
+    pub max: Duration,
+    /// the number of calls per latency bucket, see `LATENCY_BOUNDS`
+    pub histogram: [u64; LATENCY_BOUNDS.len() + 1],
+}
+
+impl CallStats {
+    /// Returns the mean time spent in a call
+    pub fn mean(&self) -> Duration {
+        if self.calls == 0 {
+            Duration::default()
+        } else {
+            Duration::from_secs_f64(self.total.as_secs_f64() / self.calls as f64)
+        }
+    }
+
+    fn record(&mut self, elapsed: Duration, is_error: bool) {
+        self.calls += 1;
+        if is_error {
+            self.errors += 1;
+        }
+        self.total += elapsed;
+        self.max = self.max.max(elapsed);
+        let bucket = LATENCY_BOUNDS
+            .iter()
+            .position(|bound| elapsed <= *bound)
+            .unwrap_or(LATENCY_BOUNDS.len());
+        self.histogram[bucket] += 1;
+    }
+}
+
+/// Counts calls and errors and records latencies per function
+///
+/// Keep an `Arc` of it to read the statistics after adding a clone of the
+/// `Arc` to a module.
+#[derive(Debug, Default)]
+pub struct MetricsHook {
+    stats: Mutex<BTreeMap<String, CallStats>>,
+}
+
+impl MetricsHook {
+    /// Creates a new hook without any statistics
+    pub fn new() -> MetricsHook {
+        MetricsHook::default()
+    }
+
+    /// Returns the statistics of a function, if it has been called
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the function
+    pub fn get(&self, name: &str) -> Option<CallStats> {
+        self.stats.lock().unwrap().get(name).cloned()
+    }
+
+    /// Returns the statistics of every function called
+    pub fn snapshot(&self) -> BTreeMap<String, CallStats> {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// Discards all statistics
+    pub fn reset(&self) {
+        self.stats.lock().unwrap().clear();
+    }
+}
+
+impl CallHook for MetricsHook {
+    fn after(&self, name: &str, _args: &[Borrowed<'_>], rtn: &Value, elapsed: Duration) {
+        let is_error = CallError::from_value(rtn).is_some();
+        let mut stats = self.stats.lock().unwrap();
+        match stats.get_mut(name) {
+            Some(stats) => stats.record(elapsed, is_error),
+            None => {
+                let mut new_stats = CallStats::default();
+                new_stats.record(elapsed, is_error);
+                stats.insert(String::from(name), new_stats);
+            }
+        }
+    }
+}
+
+/// Logs every call with the `log` crate under the target `dy`
+///
+/// Calls returning an error value are logged as warnings.
+#[cfg(feature = "log")]
+#[derive(Debug, Clone)]
+pub struct LogHook {
+    level: log::Level,
+}
+
+#[cfg(feature = "log")]
+impl LogHook {
+    /// Creates a new hook logging successful calls at the given level
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - the level of successful calls
+    pub fn new(level: log::Level) -> LogHook {
+        LogHook { level }
+    }
+}
+
+#[cfg(feature = "log")]
+impl Default for LogHook {
+    fn default() -> LogHook {
+        LogHook::new(log::Level::Debug)
+    }
+}
+
+#[cfg(feature = "log")]
+impl CallHook for LogHook {
+    fn after(&self, name: &str, args: &[Borrowed<'_>], rtn: &Value, elapsed: Duration) {
+        match CallError::from_value(rtn) {
+            Some(err) => log::warn!(target: "dy", "{} failed after {:?}: {}", name, elapsed, err),
+            None => log::log!(
+                target: "dy",
+                self.level,
+                "{} with {} arguments returned {:?} in {:?}",
+                name,
+                args.len(),
+                rtn.get_type(),
+                elapsed
+            ),
+        }
+    }
+}
+
+/// Emits an event with the `tracing` crate under the target `dy` for every call
+///
+/// Successful calls are emitted at the `DEBUG` level, calls returning an
+/// error value at the `WARN` level.
+#[cfg(feature = "tracing")]
+#[derive(Debug, Clone, Default)]
+pub struct TracingHook;
+
+#[cfg(feature = "tracing")]
+impl CallHook for TracingHook {
+    fn after(&self, name: &str, args: &[Borrowed<'_>], rtn: &Value, elapsed: Duration) {
+        let elapsed_us = elapsed.as_micros() as u64;
+        match CallError::from_value(rtn) {
+            Some(err) => tracing::warn!(
+                target: "dy",
+                function = name,
+                args = args.len(),
+                elapsed_us,
+                error = %err,
+                "call failed"
+            ),
+            None => tracing::debug!(
+                target: "dy",
+                function = name,
+                args = args.len(),
+                elapsed_us,
+                "call returned"
+            ),
+        }
+    }
+}
@@ -1,54 +1,286 @@
+use crate::abi::{AbiVersion, ABI_VERSION_SYMBOL};
+use crate::api::ModuleApi;
+use crate::convert::{FromValue, IntoArgs};
+use crate::error::{into_result, CallError};
+use crate::exported::RawFunction;
+use crate::hook::CallHook;
+use crate::lifecycle::{INIT_SYMBOL, SHUTDOWN_SYMBOL};
+use crate::loader::{LoadError, ModuleLoader};
+use crate::manifest::{FunctionInfo, MANIFEST_SYMBOL};
+use crate::object::{current_table, share_library, ObjectTable, ATTACH_SYMBOL};
 use crate::value::*;
 use libloading::{Library, Symbol};
-use std::env::current_dir;
-use std::path::PathBuf;
+use std::borrow::Cow;
+use std::marker::PhantomData;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 /// Indicates a DLL using `dy`
+///
+/// The library is shared with the objects the DLL made, e.g. handles, so
+/// that it stays loaded until the last of them is dropped.
 pub struct Module {
-    lib: Library,
+    lib: Arc<Library>,
+    hooks: Vec<Arc<dyn CallHook>>,
+    lifecycle: Mutex<Lifecycle>,
+}
+
+/// Indicates how far a `Module` has gone through `initialize` and `shutdown`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Lifecycle {
+    Loaded,
+    Initialized,
+    ShutDown,
 }
 
 /// Indicates an exported function using `dy`
 pub struct Function<'lib> {
-    sym: Symbol<'lib, unsafe extern "C" fn(args: *const ValuePtr, len: usize) -> ValuePtr>,
+    raw: RawFunction,
+    name: Cow<'lib, str>,
+    hooks: &'lib [Arc<dyn CallHook>],
+}
+
+/// Indicates an exported function called with Rust types
+///
+/// `Args` is usually a tuple of `IntoValue` types and `Ret` a `FromValue` type.
+pub struct TypedFunction<'lib, Args, Ret> {
+    func: Function<'lib>,
+    phantom: PhantomData<fn(Args) -> Ret>,
+}
+
+/// Describes how the name of a DLL is turned into file names
+#[derive(Debug, Clone, PartialEq)]
+pub struct Naming {
+    prefix: String,
+    suffixes: Vec<String>,
+    versions: Vec<String>,
 }
 
-#[cfg(unix)]
-fn get_dll_path(name: &str, dir: &str) -> PathBuf {
-    let mut rtn = PathBuf::new();
-    rtn.push(current_dir().unwrap());
-    rtn.push(dir);
-    rtn.push(format!("lib{}.so", name));
-    rtn
+impl Naming {
+    /// Creates a new naming scheme
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - the prefix put in front of the name, e.g. `lib`
+    /// * `suffix` - the suffix put after the name, e.g. `.so`
+    pub fn new(prefix: &str, suffix: &str) -> Naming {
+        Naming {
+            prefix: String::from(prefix),
+            suffixes: vec![String::from(suffix)],
+            versions: Vec::new(),
+        }
+    }
+
+    /// Returns the naming scheme of the current platform
+    pub fn platform() -> Naming {
+        if cfg!(windows) {
+            Naming::new("", ".dll")
+        } else if cfg!(target_os = "macos") {
+            Naming::new("lib", ".dylib").suffix(".so")
+        } else {
+            Naming::new("lib", ".so")
+        }
+    }
+
+    /// Adds an alternative suffix, tried after the previous ones
+    ///
+    /// # Arguments
+    ///
+    /// * `suffix` - the suffix to add
+    pub fn suffix(mut self, suffix: &str) -> Naming {
+        self.suffixes.push(String::from(suffix));
+        self
+    }
+
+    /// Adds a version appended after the suffix, e.g. `1` for `libfoo.so.1`
+    ///
+    /// # Arguments
+    ///
+    /// * `version` - the version to add
+    pub fn version(mut self, version: &str) -> Naming {
+        self.versions.push(String::from(version));
+        self
+    }
+
+    /// Returns the candidate file names of a DLL in the order they are tried
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the DLL
+    pub fn file_names(&self, name: &str) -> Vec<String> {
+        let mut rtn = Vec::new();
+        for suffix in self.suffixes.iter() {
+            let file_name = format!("{}{}{}", self.prefix, name, suffix);
+            for version in self.versions.iter() {
+                rtn.push(format!("{}.{}", file_name, version));
+            }
+            rtn.push(file_name);
+        }
+        rtn
+    }
+
+    /// Returns the name of the DLL a file name belongs to, if it follows this naming scheme
+    ///
+    /// # Arguments
+    ///
+    /// * `file_name` - the file name, e.g. `libfoo.so.1`
+    pub fn module_name(&self, file_name: &str) -> Option<String> {
+        let name = file_name.strip_prefix(self.prefix.as_str())?;
+        for suffix in self.suffixes.iter() {
+            if let Some(name) = name.strip_suffix(suffix.as_str()) {
+                return Some(String::from(name)).filter(|name| !name.is_empty());
+            }
+            for version in self.versions.iter() {
+                let versioned = format!("{}.{}", suffix, version);
+                if let Some(name) = name.strip_suffix(versioned.as_str()) {
+                    return Some(String::from(name)).filter(|name| !name.is_empty());
+                }
+            }
+        }
+        None
+    }
 }
 
-#[cfg(windows)]
-fn get_dll_path(name: &str, dir: &str) -> PathBuf {
-    let mut rtn = PathBuf::new();
-    rtn.push(current_dir().unwrap());
-    rtn.push(dir);
-    rtn.push(format!("{}.dll", name));
-    rtn
+impl Default for Naming {
+    fn default() -> Naming {
+        Naming::platform()
+    }
 }
 
 impl Module {
     /// Creates a new `Module` instance from an existing DLL
+    ///
+    /// Returns `None` if the DLL cannot be found or opened, or if it exports an
+    /// `AbiVersion` incompatible with the current binary; `try_new` tells why.
     /// 
     /// # Arguments
     /// 
     /// * `name` - the name of the DLL
     /// * `search_paths` - the list of directories where the DLL may be located in
     pub fn new(name: &str, search_paths: &[&str]) -> Option<Module> {
-        for search_path in search_paths {
-            let dll_path = get_dll_path(name, search_path);
-            if dll_path.exists() {
-                return match Library::new(dll_path) {
-                    Ok(lib) => Some(Module { lib: lib }),
-                    Err(_) => None,
-                };
+        Module::find(name, search_paths, &Naming::platform(), false)
+    }
+
+    /// Creates a new `Module` instance from an existing DLL, reporting why it
+    /// could not be loaded, e.g. every path tried
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the DLL
+    /// * `search_paths` - the list of directories where the DLL may be located in
+    pub fn try_new(name: &str, search_paths: &[&str]) -> Result<Module, LoadError> {
+        ModuleLoader::new().dirs(search_paths).load(name)
+    }
+
+    /// Creates a new `Module` instance using a custom naming scheme
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the DLL
+    /// * `search_paths` - the list of directories where the DLL may be located in
+    /// * `naming` - the naming scheme used to make file names from `name`
+    /// * `system_search` - if `true`, falls back to the search of the OS loader
+    ///   (`LD_LIBRARY_PATH`, rpath, `PATH`, ...) when no search path contains the DLL
+    ///
+    /// Use `ModuleLoader` to find out why a DLL could not be loaded.
+    pub fn find(
+        name: &str,
+        search_paths: &[&str],
+        naming: &Naming,
+        system_search: bool,
+    ) -> Option<Module> {
+        ModuleLoader::new()
+            .dirs(search_paths)
+            .naming(naming.clone())
+            .system_search(system_search)
+            .load(name)
+            .ok()
+    }
+
+    /// Creates a new `Module` instance from the DLL at the exact given path
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the path of the DLL
+    pub fn open<P: AsRef<Path>>(path: P) -> Option<Module> {
+        ModuleLoader::new().open(path).ok()
+    }
+
+    /// Opens the DLL at the given path and verifies its `AbiVersion`,
+    /// reporting why it could not be opened
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the path of the DLL, or a bare file name for the search of the OS loader
+    /// * `allow_unversioned` - whether a DLL not exporting its `AbiVersion` is accepted
+    pub(crate) fn load(path: &Path, allow_unversioned: bool) -> Result<Module, LoadError> {
+        let lib = match Library::new(path) {
+            Ok(lib) => lib,
+            Err(err) => {
+                return Err(LoadError::Open {
+                    path: path.to_path_buf(),
+                    message: err.to_string(),
+                })
+            }
+        };
+
+        let abi_version: Result<Symbol<extern "C" fn() -> AbiVersion>, _> =
+            unsafe { lib.get(ABI_VERSION_SYMBOL.as_bytes()) };
+        match abi_version {
+            Ok(abi_version) => {
+                let expected = AbiVersion::current();
+                let found = abi_version();
+                if !found.is_compatible(&expected) {
+                    return Err(LoadError::AbiMismatch {
+                        path: path.to_path_buf(),
+                        expected,
+                        found,
+                    });
+                }
             }
+            Err(_) if !allow_unversioned => {
+                return Err(LoadError::MissingAbiVersion {
+                    path: path.to_path_buf(),
+                })
+            }
+            Err(_) => {}
         }
-        None
+
+        // objects are passed by id, so the DLL must find them in our table
+        let attach: Option<unsafe extern "C" fn(*const ObjectTable, u64)> =
+            unsafe { lib.get(ATTACH_SYMBOL.as_bytes()) }
+                .ok()
+                .map(|attach: Symbol<_>| *attach);
+        let lib = match attach {
+            Some(attach) => {
+                // the address of `dy_attach` tells the objects of the DLL apart
+                let token = attach as usize as u64;
+                let lib = share_library(token, lib);
+                unsafe { attach(current_table(), token) };
+                lib
+            }
+            None => Arc::new(lib),
+        };
+
+        Ok(Module {
+            lib,
+            hooks: Vec::new(),
+            lifecycle: Mutex::new(Lifecycle::Loaded),
+        })
+    }
+
+    /// Lists the functions the DLL declares in its manifest
+    ///
+    /// Returns an empty list if the DLL does not export a manifest.
+    pub fn functions(&self) -> Vec<FunctionInfo> {
+        let manifest: Symbol<unsafe extern "C" fn() -> ValuePtr> =
+            match unsafe { self.lib.get(MANIFEST_SYMBOL.as_bytes()) } {
+                Ok(sym) => sym,
+                Err(_) => return Vec::new(),
+            };
+        let manifest = unsafe { Owned::from_ptr(manifest()) };
+        FunctionInfo::list_from_value(&manifest)
     }
 
     /// Retrieves an exported function from the DLL
@@ -57,14 +289,220 @@ impl Module {
     /// 
     /// * `name` - the name of the function
     pub fn get_fn<'lib>(&'lib self, name: &str) -> Option<Function<'lib>> {
-        match unsafe { self.lib.get(name.as_bytes()) } {
-            Ok(sym) => Some(Function { sym: sym }),
-            Err(_) => None,
+        Some(Function {
+            raw: self.symbol(name)?,
+            name: Cow::Owned(String::from(name)),
+            hooks: &self.hooks,
+        })
+    }
+
+    /// Retrieves an exported function borrowing its name, so that calls by
+    /// name copy nothing
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the function
+    pub(crate) fn get_fn_by_ref<'a>(&'a self, name: &'a str) -> Option<Function<'a>> {
+        Some(Function::with_hooks(name, self.symbol(name)?, &self.hooks))
+    }
+
+    fn symbol(&self, name: &str) -> Option<RawFunction> {
+        let sym: Symbol<RawFunction> = unsafe { self.lib.get(name.as_bytes()) }.ok()?;
+        Some(*sym)
+    }
+
+    /// Adds a hook observing every call of the functions retrieved afterwards
+    ///
+    /// # Arguments
+    ///
+    /// * `hook` - the hook
+    pub fn add_hook<H: CallHook + 'static>(&mut self, hook: H) {
+        self.hooks.push(Arc::new(hook));
+    }
+
+    /// Runs the `#[dy::init]` function of the DLL, if any
+    ///
+    /// Fails if the function fails, or if the DLL has already been initialized
+    /// or shut down.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - the configuration passed to the function
+    pub fn initialize(&self, config: &Value) -> Result<(), CallError> {
+        let mut lifecycle = self.lifecycle.lock().unwrap();
+        match *lifecycle {
+            Lifecycle::Loaded => {}
+            Lifecycle::Initialized => {
+                return Err(CallError::from("the module is already initialized"))
+            }
+            Lifecycle::ShutDown => return Err(CallError::from("the module has been shut down")),
         }
+        if let Some(init) = self.get_fn_by_ref(INIT_SYMBOL) {
+            init.try_call_with_borrowed(&[config.borrow()])?;
+        }
+        *lifecycle = Lifecycle::Initialized;
+        Ok(())
+    }
+
+    /// Returns `true` if `initialize` has succeeded and the DLL has not been shut down
+    pub fn is_initialized(&self) -> bool {
+        *self.lifecycle.lock().unwrap() == Lifecycle::Initialized
+    }
+
+    /// Runs the `#[dy::shutdown]` function of the DLL, if any, which dropping the
+    /// `Module` does otherwise
+    ///
+    /// The function runs at most once, and not at all if the DLL has an
+    /// `#[dy::init]` function which has not succeeded. No function of the DLL
+    /// should be called afterwards.
+    pub fn shutdown(&self) -> Result<(), CallError> {
+        let mut lifecycle = self.lifecycle.lock().unwrap();
+        let run = match *lifecycle {
+            Lifecycle::Loaded => self.symbol(INIT_SYMBOL).is_none(),
+            Lifecycle::Initialized => true,
+            Lifecycle::ShutDown => false,
+        };
+        *lifecycle = Lifecycle::ShutDown;
+        match self.get_fn_by_ref(SHUTDOWN_SYMBOL) {
+            Some(shutdown) if run => shutdown.try_call(Vec::new()).map(|_| ()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Retrieves an exported function called with Rust types
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the function
+    pub fn get_typed_fn<'lib, Args: IntoArgs, Ret: FromValue>(
+        &'lib self,
+        name: &str,
+    ) -> Option<TypedFunction<'lib, Args, Ret>> {
+        self.get_fn(name).map(|func| TypedFunction {
+            func,
+            phantom: PhantomData,
+        })
+    }
+}
+
+impl Drop for Module {
+    fn drop(&mut self) {
+        // errors cannot be reported here; call `shutdown` to see them
+        let _ = self.shutdown();
+    }
+}
+
+impl ModuleApi for Module {
+    fn functions(&self) -> Vec<FunctionInfo> {
+        Module::functions(self)
+    }
+
+    fn has_fn(&self, name: &str) -> bool {
+        self.symbol(name).is_some()
+    }
+
+    fn call_with_borrowed(&self, name: &str, args: &[Borrowed<'_>]) -> Option<Owned> {
+        self.get_fn_by_ref(name).map(|func| func.call_with_borrowed(args))
+    }
+
+    fn call(&self, name: &str, args: Vec<Owned>) -> Option<Owned> {
+        self.get_fn_by_ref(name).map(|func| func.call(args))
     }
 }
 
 impl<'lib> Function<'lib> {
+    /// Creates a new `Function` instance from a function pointer
+    ///
+    /// The function runs no `CallHook`, whatever the DLL exporting it, and
+    /// its name is empty; use `from_raw_in` to run the hooks of the DLL.
+    ///
+    /// # Safety
+    ///
+    /// `raw` must be a function exported using `dy` by a DLL which outlives `'lib`.
+    ///
+    /// # Arguments
+    ///
+    /// * `raw` - the function pointer
+    pub unsafe fn from_raw(raw: RawFunction) -> Function<'lib> {
+        Function {
+            raw,
+            name: Cow::Borrowed(""),
+            hooks: &[],
+        }
+    }
+
+    /// Creates a new `Function` instance from a function pointer, running the
+    /// hooks of the DLL exporting it
+    ///
+    /// # Safety
+    ///
+    /// `raw` must be a function exported by `module`.
+    ///
+    /// # Arguments
+    ///
+    /// * `module` - the DLL exporting the function
+    /// * `name` - the name of the function
+    /// * `raw` - the function pointer
+    pub unsafe fn from_raw_in(
+        module: &'lib Module,
+        name: &'lib str,
+        raw: RawFunction,
+    ) -> Function<'lib> {
+        Function::with_hooks(name, raw, &module.hooks)
+    }
+
+    /// Creates a new `Function` instance running the given hooks
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the function
+    /// * `raw` - the function pointer, valid for `'lib`
+    /// * `hooks` - the hooks
+    pub(crate) fn with_hooks(
+        name: &'lib str,
+        raw: RawFunction,
+        hooks: &'lib [Arc<dyn CallHook>],
+    ) -> Function<'lib> {
+        Function {
+            raw,
+            name: Cow::Borrowed(name),
+            hooks,
+        }
+    }
+
+    /// Returns the name of the function, empty if made by `from_raw`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the function pointer, valid as long as the DLL is loaded
+    pub fn as_raw(&self) -> RawFunction {
+        self.raw
+    }
+
+    fn invoke(&self, args: &[ValuePtr]) -> Owned {
+        let rtn = unsafe { (self.raw)(args.as_ptr(), args.len()) };
+        unsafe { Owned::from_ptr(rtn) }
+    }
+
+    fn invoke_hooked(&self, args: &[ValuePtr]) -> Owned {
+        if self.hooks.is_empty() {
+            return self.invoke(args);
+        }
+        let borrowed: Vec<Borrowed<'_>> =
+            args.iter().map(|ptr| unsafe { Borrowed::from_ptr(*ptr) }).collect();
+        for hook in self.hooks {
+            hook.before(&self.name, &borrowed);
+        }
+        let start = Instant::now();
+        let rtn = self.invoke(args);
+        let elapsed = start.elapsed();
+        for hook in self.hooks {
+            hook.after(&self.name, &borrowed, &rtn, elapsed);
+        }
+        rtn
+    }
+
     /// Invokes the exported function
     /// 
     /// # Arguments
@@ -72,8 +510,7 @@ impl<'lib> Function<'lib> {
     /// * `args` - the arguments
     pub fn call_with_borrowed(&self, args: &[Borrowed<'_>]) -> Owned {
         let list_ptr: Vec<ValuePtr> = args.iter().map(|arg| arg.get_ptr()).collect();
-        let rtn = unsafe { (self.sym)(list_ptr.as_ptr(), list_ptr.len()) };
-        unsafe { Owned::from_ptr(rtn) }
+        self.invoke_hooked(&list_ptr)
     }
 
     /// Calls the exported function and disposes arguments after the invocation
@@ -83,11 +520,149 @@ impl<'lib> Function<'lib> {
     /// * `args` - the arguments
     pub fn call(&self, args: Vec<Owned>) -> Owned {
         let list_ptr: Vec<ValuePtr> = args.into_iter().map(|arg| arg.into_ptr()).collect();
-        let rtn = unsafe { (self.sym)(list_ptr.as_ptr(), list_ptr.len()) };
-        let rtn = unsafe { Owned::from_ptr(rtn) };
+        let rtn = self.invoke_hooked(&list_ptr);
         for ptr in list_ptr {
             unsafe { Owned::from_ptr(ptr) };
         }
         rtn
     }
+
+    /// Invokes the exported function, reporting a panic or an error of the function as `CallError`
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments
+    pub fn try_call_with_borrowed(&self, args: &[Borrowed<'_>]) -> Result<Owned, CallError> {
+        into_result(self.call_with_borrowed(args))
+    }
+
+    /// Calls the exported function and disposes arguments after the invocation,
+    /// reporting a panic or an error of the function as `CallError`
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments
+    pub fn try_call(&self, args: Vec<Owned>) -> Result<Owned, CallError> {
+        into_result(self.call(args))
+    }
+
+    /// Calls the exported function with Rust types, converting the result back
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments, usually a tuple
+    pub fn call_typed<Args: IntoArgs, Ret: FromValue>(
+        &self,
+        args: Args,
+    ) -> Result<Ret, CallError> {
+        let rtn = self.try_call(args.into_args())?;
+        Ret::from_value(&rtn).map_err(CallError::unexpected_return)
+    }
+}
+
+impl<'lib, Args: IntoArgs, Ret: FromValue> TypedFunction<'lib, Args, Ret> {
+    /// Calls the exported function, converting the result back
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments, usually a tuple
+    pub fn call(&self, args: Args) -> Result<Ret, CallError> {
+        self.func.call_typed(args)
+    }
+}
+
+/// Declares a struct wrapping a DLL with a typed method for every function
+///
+/// Every function is resolved when the DLL is loaded; loading fails with
+/// `LoadError::MissingFunctions` if any of them is not exported. The struct has
+/// `new(search_paths)`, `load(&ModuleLoader)` and `from_module(Module)`
+/// constructors and a `module()` getter, and each method returns
+/// `Result<Ret, CallError>`.
+///
+/// ```ignore
+/// dy::import_module! {
+///     /// The math plugin
+///     pub struct Math = "mathplugin";
+///     fn scale(x: f64, k: f64) -> f64;
+///     fn reset();
+/// }
+///
+/// let math = Math::new(&["plugins"])?;
+/// let y = math.scale(2.0, 1.5)?;
+/// ```
+#[macro_export]
+macro_rules! import_module {
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident = $lib:literal;
+        $(
+            $(#[$fn_attr:meta])*
+            fn $fn_name:ident ( $($param:ident : $ty:ty),* $(,)? ) $(-> $ret:ty)? ;
+        )*
+    ) => {
+        $(#[$attr])*
+        $vis struct $name {
+            __module: $crate::Module,
+            $($fn_name: $crate::RawFunction,)*
+        }
+
+        #[allow(dead_code)]
+        impl $name {
+            /// The name of the DLL
+            pub const NAME: &'static str = $lib;
+
+            /// Loads the DLL from the given directories
+            pub fn new(search_paths: &[&str]) -> Result<$name, $crate::LoadError> {
+                $name::load(&$crate::ModuleLoader::new().dirs(search_paths))
+            }
+
+            /// Loads the DLL using the given loader
+            pub fn load(loader: &$crate::ModuleLoader) -> Result<$name, $crate::LoadError> {
+                $name::from_module(loader.load($lib)?)
+            }
+
+            /// Resolves the functions of a loaded DLL
+            pub fn from_module(module: $crate::Module) -> Result<$name, $crate::LoadError> {
+                let mut missing: Vec<String> = Vec::new();
+                $(
+                    let $fn_name = module.get_fn(stringify!($fn_name)).map(|f| f.as_raw());
+                    if $fn_name.is_none() {
+                        missing.push(String::from(stringify!($fn_name)));
+                    }
+                )*
+                if !missing.is_empty() {
+                    return Err($crate::LoadError::MissingFunctions(missing));
+                }
+                Ok($name {
+                    __module: module,
+                    $($fn_name: $fn_name.unwrap(),)*
+                })
+            }
+
+            /// Returns the wrapped DLL
+            pub fn module(&self) -> &$crate::Module {
+                &self.__module
+            }
+
+            $(
+                $(#[$fn_attr])*
+                pub fn $fn_name(
+                    &self,
+                    $($param: $ty),*
+                ) -> Result<$crate::import_module!(@ret $($ret)?), $crate::CallError> {
+                    // the function pointer lives as long as `__module`
+                    let func = unsafe {
+                        $crate::Function::from_raw_in(
+                            &self.__module,
+                            stringify!($fn_name),
+                            self.$fn_name,
+                        )
+                    };
+                    func.call_typed(($($param,)*))
+                }
+            )*
+        }
+    };
+    (@ret) => { () };
+    (@ret $ret:ty) => { $ret };
 }
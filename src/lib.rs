@@ -1,12 +1,92 @@
 mod bindings;
 
+mod abi;
+pub use abi::*;
+
+mod api;
+pub use api::*;
+
+#[cfg(any(feature = "builtin", feature = "export"))]
+mod builtin;
+#[cfg(any(feature = "builtin", feature = "export"))]
+pub use builtin::*;
+#[cfg(any(feature = "builtin", feature = "export"))]
+#[doc(hidden)]
+pub use inventory;
+
+mod callback;
+pub use callback::*;
+
+mod cancel;
+pub use cancel::*;
+
+pub mod codec;
+
+mod convert;
+pub use convert::*;
+
+mod error;
+pub use error::*;
+
+mod exported;
+pub use exported::*;
+
+mod handle;
+pub use handle::*;
+
+#[cfg(feature = "import")]
+mod hook;
+#[cfg(feature = "import")]
+pub use hook::*;
 #[cfg(feature = "import")]
 mod import;
 #[cfg(feature = "import")]
 pub use import::*;
+#[cfg(feature = "import")]
+mod loader;
+#[cfg(feature = "import")]
+pub use loader::*;
+#[cfg(feature = "import")]
+mod pool;
+#[cfg(feature = "import")]
+pub use pool::{set_max_workers, CallFuture, DEFAULT_MAX_WORKERS};
+#[cfg(feature = "import")]
+pub mod registry;
+#[cfg(feature = "import")]
+mod record;
+#[cfg(feature = "import")]
+pub use record::*;
+#[cfg(feature = "import")]
+mod reload;
+#[cfg(feature = "import")]
+pub mod remote;
+#[cfg(feature = "import")]
+pub use reload::*;
+#[cfg(feature = "import")]
+mod shared;
+#[cfg(feature = "import")]
+pub use shared::*;
+
+#[cfg(feature = "import")]
+mod lifecycle;
+
+mod manifest;
+pub use manifest::*;
+
+mod object;
+#[doc(hidden)]
+pub use object::{__attach_objects, ObjectTable};
+
+mod mock;
+pub use mock::*;
+
+mod replay;
+pub use replay::*;
+
+pub mod text;
 
 mod value;
 pub use value::*;
 
 #[cfg(feature = "export")]
-pub use dy_export::dy_export as export;
\ No newline at end of file
+pub use dy_macros::{export, init, shutdown};
\ No newline at end of file
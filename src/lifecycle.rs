@@ -0,0 +1,5 @@
+/// The name of the symbol initializing a DLL, made by `#[dy::init]`
+pub(crate) const INIT_SYMBOL: &str = "dy_init";
+
+/// The name of the symbol shutting a DLL down, made by `#[dy::shutdown]`
+pub(crate) const SHUTDOWN_SYMBOL: &str = "dy_shutdown";
@@ -0,0 +1,332 @@
+use crate::abi::AbiVersion;
+use crate::import::{Module, Naming};
+use std::env::{current_dir, current_exe, split_paths, var_os};
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// The environment variable conventionally holding the directories of DLLs
+pub const MODULE_PATH_VAR: &str = "DY_MODULE_PATH";
+
+/// Indicates the reason why a DLL could not be loaded
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadError {
+    /// None of the candidate paths contained the DLL
+    NotFound {
+        /// the name of the DLL
+        name: String,
+        /// every path tried, in order
+        tried: Vec<PathBuf>,
+    },
+    /// The DLL was found but the OS loader refused to open it
+    Open {
+        /// the path of the DLL
+        path: PathBuf,
+        /// the message reported by the OS loader
+        message: String,
+    },
+    /// The DLL does not export its `AbiVersion`, i.e. it does not link `dy` with
+    /// the `export` feature
+    MissingAbiVersion {
+        /// the path of the DLL
+        path: PathBuf,
+    },
+    /// The DLL was built against an incompatible binary interface
+    AbiMismatch {
+        /// the path of the DLL
+        path: PathBuf,
+        /// the `AbiVersion` of the current binary
+        expected: AbiVersion,
+        /// the `AbiVersion` of the DLL
+        found: AbiVersion,
+    },
+    /// The DLL does not export some of the required functions
+    MissingFunctions(Vec<String>),
+    /// A new version of a `ReloadableModule` failed to initialize
+    Initialize {
+        /// the path of the DLL
+        path: PathBuf,
+        /// the message of the error returned by the initialization
+        message: String,
+    },
+    /// Functions of the same name are registered by several crates linked into
+    /// the binary
+    DuplicateFunctions(Vec<String>),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::NotFound { name, tried } => {
+                write!(f, "could not find module `{}`; tried:", name)?;
+                for path in tried {
+                    write!(f, "\n  {}", path.display())?;
+                }
+                Ok(())
+            }
+            LoadError::Open { path, message } => {
+                write!(f, "could not open `{}`: {}", path.display(), message)
+            }
+            LoadError::MissingAbiVersion { path } => write!(
+                f,
+                "`{}` does not export its dy ABI version; was it built with dy's `export` feature?",
+                path.display()
+            ),
+            LoadError::AbiMismatch {
+                path,
+                expected,
+                found,
+            } => write!(
+                f,
+                "`{}` uses an incompatible dy ABI: expected {}, found {}",
+                path.display(),
+                expected,
+                found
+            ),
+            LoadError::MissingFunctions(names) => write!(
+                f,
+                "the module does not export the functions: {}",
+                names.join(", ")
+            ),
+            LoadError::Initialize { path, message } => {
+                write!(f, "could not initialize `{}`: {}", path.display(), message)
+            }
+            LoadError::DuplicateFunctions(names) => write!(
+                f,
+                "several crates register the functions: {}",
+                names.join(", ")
+            ),
+        }
+    }
+}
+
+impl Error for LoadError {}
+
+/// Builds the list of directories searched for DLLs and loads them
+///
+/// Directories are searched in the order they were added.
+#[derive(Debug, Clone)]
+pub struct ModuleLoader {
+    dirs: Vec<PathBuf>,
+    naming: Naming,
+    system_search: bool,
+    allow_unversioned: bool,
+}
+
+/// Resolves a search path; relative paths are relative to the current directory
+fn resolve_dir(dir: &Path) -> PathBuf {
+    if dir.is_absolute() {
+        dir.to_path_buf()
+    } else {
+        match current_dir() {
+            Ok(cwd) => cwd.join(dir),
+            Err(_) => dir.to_path_buf(),
+        }
+    }
+}
+
+impl Default for ModuleLoader {
+    fn default() -> ModuleLoader {
+        ModuleLoader {
+            dirs: Vec::new(),
+            naming: Naming::default(),
+            system_search: false,
+            allow_unversioned: true,
+        }
+    }
+}
+
+impl ModuleLoader {
+    /// Creates a new loader without any search directory
+    pub fn new() -> ModuleLoader {
+        ModuleLoader::default()
+    }
+
+    /// Adds a directory; relative directories are relative to the current directory
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - the directory to add
+    pub fn dir<P: AsRef<Path>>(mut self, dir: P) -> ModuleLoader {
+        self.dirs.push(resolve_dir(dir.as_ref()));
+        self
+    }
+
+    /// Adds several directories
+    ///
+    /// # Arguments
+    ///
+    /// * `dirs` - the directories to add
+    pub fn dirs<P: AsRef<Path>>(self, dirs: &[P]) -> ModuleLoader {
+        dirs.iter().fold(self, |loader, dir| loader.dir(dir))
+    }
+
+    /// Adds the directories listed in an environment variable, separated like `PATH`
+    ///
+    /// Does nothing if the variable is not set.
+    ///
+    /// # Arguments
+    ///
+    /// * `var` - the name of the variable, usually `MODULE_PATH_VAR`
+    pub fn env(self, var: &str) -> ModuleLoader {
+        match var_os(var) {
+            Some(paths) => split_paths(&paths)
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .fold(self, |loader, dir| loader.dir(dir)),
+            None => self,
+        }
+    }
+
+    /// Adds the directory containing the current executable
+    pub fn exe_dir(self) -> ModuleLoader {
+        match current_exe() {
+            Ok(exe) => match exe.parent() {
+                Some(dir) => self.dir(dir),
+                None => self,
+            },
+            Err(_) => self,
+        }
+    }
+
+    /// Adds `subdir` of every XDG data directory, i.e. `$XDG_DATA_HOME`
+    /// followed by `$XDG_DATA_DIRS`, with their default values if unset
+    ///
+    /// # Arguments
+    ///
+    /// * `subdir` - the directory relative to each data directory, e.g. `myapp/plugins`
+    pub fn xdg_data_dirs(self, subdir: &str) -> ModuleLoader {
+        let mut data_dirs = Vec::new();
+        match var_os("XDG_DATA_HOME") {
+            Some(dir) if !dir.is_empty() => data_dirs.push(PathBuf::from(dir)),
+            _ => {
+                if let Some(home) = var_os("HOME") {
+                    data_dirs.push(Path::new(&home).join(".local/share"));
+                }
+            }
+        }
+        match var_os("XDG_DATA_DIRS") {
+            Some(dirs) if !dirs.is_empty() => data_dirs.extend(split_paths(&dirs)),
+            _ => data_dirs.extend(split_paths("/usr/local/share:/usr/share")),
+        }
+
+        data_dirs
+            .into_iter()
+            .filter(|dir| dir.is_absolute())
+            .fold(self, |loader, dir| loader.dir(dir.join(subdir)))
+    }
+
+    /// Sets the naming scheme used to make file names, `Naming::platform()` by default
+    ///
+    /// # Arguments
+    ///
+    /// * `naming` - the naming scheme
+    pub fn naming(mut self, naming: Naming) -> ModuleLoader {
+        self.naming = naming;
+        self
+    }
+
+    /// Returns the naming scheme used to make file names
+    pub fn naming_scheme(&self) -> &Naming {
+        &self.naming
+    }
+
+    /// Sets whether the search of the OS loader (`LD_LIBRARY_PATH`, rpath, `PATH`, ...)
+    /// is used when no directory contains the DLL, `false` by default
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - whether to fall back to the OS loader
+    pub fn system_search(mut self, enabled: bool) -> ModuleLoader {
+        self.system_search = enabled;
+        self
+    }
+
+    /// Sets whether DLLs not exporting their `AbiVersion` are loaded anyway, `true` by default
+    ///
+    /// DLLs exporting an incompatible `AbiVersion` are always rejected. Disable it
+    /// to also reject DLLs built without the `export` feature, such as plain C DLLs.
+    ///
+    /// # Arguments
+    ///
+    /// * `allowed` - whether unversioned DLLs are accepted
+    pub fn allow_unversioned(mut self, allowed: bool) -> ModuleLoader {
+        self.allow_unversioned = allowed;
+        self
+    }
+
+    /// Returns whether DLLs not exporting their `AbiVersion` are loaded anyway
+    pub fn allows_unversioned(&self) -> bool {
+        self.allow_unversioned
+    }
+
+    /// Returns the directories searched, in order
+    pub fn search_paths(&self) -> &[PathBuf] {
+        &self.dirs
+    }
+
+    /// Loads a DLL from the first directory containing it
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the DLL
+    pub fn load(&self, name: &str) -> Result<Module, LoadError> {
+        self.load_candidates(name, &self.candidates(name))
+    }
+
+    /// Returns the paths `load` tries, in order; bare file names are left to the OS loader
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the DLL
+    pub(crate) fn candidates(&self, name: &str) -> Vec<PathBuf> {
+        let file_names = self.naming.file_names(name);
+        let mut rtn = Vec::new();
+        for dir in self.dirs.iter() {
+            for file_name in file_names.iter() {
+                rtn.push(dir.join(file_name));
+            }
+        }
+        if self.system_search {
+            rtn.extend(file_names.iter().map(PathBuf::from));
+        }
+        rtn
+    }
+
+    /// Loads a DLL from the first of the given paths containing it
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the DLL
+    /// * `candidates` - the paths to try, as returned by `candidates`
+    pub(crate) fn load_candidates(
+        &self,
+        name: &str,
+        candidates: &[PathBuf],
+    ) -> Result<Module, LoadError> {
+        let mut tried = Vec::new();
+        for dll_path in candidates {
+            if dll_path.parent() == Some(Path::new("")) {
+                match self.open(dll_path) {
+                    Err(LoadError::Open { .. }) => {}
+                    rtn => return rtn,
+                }
+            } else if dll_path.exists() {
+                return self.open(dll_path);
+            }
+            tried.push(dll_path.clone());
+        }
+        Err(LoadError::NotFound {
+            name: String::from(name),
+            tried,
+        })
+    }
+
+    /// Loads the DLL at the exact given path
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the path of the DLL
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> Result<Module, LoadError> {
+        Module::load(path.as_ref(), self.allow_unversioned)
+    }
+}
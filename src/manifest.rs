@@ -0,0 +1,105 @@
+#[cfg(feature = "export")]
+use crate::builtin::StaticFunction;
+use crate::value::*;
+
+/// The name of the symbol returning the manifest of a DLL
+pub(crate) const MANIFEST_SYMBOL: &str = "dy_manifest";
+
+/// Describes a function exported by a DLL
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionInfo {
+    /// the name of the function
+    pub name: String,
+    /// the number of arguments, `None` if the function is variadic
+    pub arity: Option<usize>,
+    /// the signature of the function in a human-readable form
+    pub signature: Option<String>,
+    /// the documentation of the function
+    pub doc: Option<String>,
+}
+
+impl FunctionInfo {
+    /// Creates a new `FunctionInfo` instance of a variadic function without documentation
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the function
+    pub fn new(name: &str) -> FunctionInfo {
+        FunctionInfo {
+            name: String::from(name),
+            arity: None,
+            signature: None,
+            doc: None,
+        }
+    }
+
+    /// Makes a generic map describing this function
+    pub fn to_value(&self) -> Owned {
+        let mut entries = vec![("name", Value::new_str(&self.name))];
+        if let Some(arity) = self.arity {
+            entries.push(("arity", Value::new_int(arity as i64)));
+        }
+        if let Some(signature) = &self.signature {
+            entries.push(("signature", Value::new_str(signature)));
+        }
+        if let Some(doc) = &self.doc {
+            entries.push(("doc", Value::new_str(doc)));
+        }
+        Value::new_map(entries)
+    }
+
+    /// Reads a generic map made by `to_value`
+    ///
+    /// # Arguments
+    ///
+    /// * `val` - the map to read
+    pub fn from_value(val: &Value) -> Option<FunctionInfo> {
+        let map = val.as_map()?;
+        let get_str = |key: &str| {
+            map.at(key)
+                .and_then(|pair| pair.get_val().as_str().map(|s| s.get()))
+        };
+        Some(FunctionInfo {
+            name: get_str("name")?,
+            arity: map
+                .at("arity")
+                .and_then(|pair| pair.get_val().as_int().map(|i| i.get() as usize)),
+            signature: get_str("signature"),
+            doc: get_str("doc"),
+        })
+    }
+
+    /// Makes a generic array describing the given functions, i.e. a manifest
+    ///
+    /// # Arguments
+    ///
+    /// * `infos` - the functions to describe
+    pub fn list_to_value(infos: &[FunctionInfo]) -> Owned {
+        Value::new_arr(infos.iter().map(|info| info.to_value()).collect())
+    }
+
+    /// Reads a manifest made by `list_to_value`, skipping malformed entries
+    ///
+    /// # Arguments
+    ///
+    /// * `val` - the manifest to read
+    pub fn list_from_value(val: &Value) -> Vec<FunctionInfo> {
+        match val.as_arr() {
+            Some(arr) => arr
+                .iter()
+                .filter_map(|elem| FunctionInfo::from_value(&elem))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Exports the manifest of the DLL, listing every function exported with
+/// `#[export]` or `exported!` by the crates linked into it, sorted by name
+#[cfg(feature = "export")]
+#[cfg_attr(not(feature = "builtin"), no_mangle)]
+pub extern "C" fn dy_manifest() -> ValuePtr {
+    let mut infos: Vec<FunctionInfo> = StaticFunction::all().map(|func| func.info()).collect();
+    infos.sort_by(|a, b| a.name.cmp(&b.name));
+    FunctionInfo::list_to_value(&infos).into_ptr()
+}
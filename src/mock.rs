@@ -0,0 +1,185 @@
+use crate::api::ModuleApi;
+use crate::codec::encode;
+use crate::manifest::FunctionInfo;
+use crate::value::*;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Mutex;
+
+type MockFn = Box<dyn Fn(&[Borrowed<'_>]) -> Owned + Send + Sync>;
+
+/// Indicates an in-memory stand-in for a DLL, for testing host code
+///
+/// Functions are Rust closures registered under a name. Every call, including
+/// calls of functions the mock does not have, is recorded with copies of its
+/// arguments.
+#[derive(Default)]
+pub struct MockModule {
+    fns: BTreeMap<String, MockFn>,
+    expected: BTreeMap<String, usize>,
+    calls: Mutex<Vec<MockCall>>,
+}
+
+/// Indicates a call recorded by a `MockModule`
+#[derive(Debug)]
+pub struct MockCall {
+    /// the name of the function
+    pub name: String,
+    /// copies of the arguments
+    pub args: Vec<Owned>,
+}
+
+impl MockModule {
+    /// Creates a new mock without any functions
+    pub fn new() -> MockModule {
+        MockModule::default()
+    }
+
+    /// Adds a function
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the function
+    /// * `f` - the closure called in place of the function
+    pub fn function<F>(mut self, name: &str, f: F) -> MockModule
+    where
+        F: Fn(&[Borrowed<'_>]) -> Owned + Send + Sync + 'static,
+    {
+        self.fns.insert(String::from(name), Box::new(f));
+        self
+    }
+
+    /// Adds a function returning a copy of the same value on every call
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the function
+    /// * `val` - the value to return
+    pub fn returning(self, name: &str, val: Owned) -> MockModule {
+        let val = SharedValue::new(val);
+        self.function(name, move |_| val.copy())
+    }
+
+    /// Expects a function to be called exactly the given number of times,
+    /// checked by `verify`
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the function
+    /// * `times` - the number of calls
+    pub fn expect(mut self, name: &str, times: usize) -> MockModule {
+        self.expected.insert(String::from(name), times);
+        self
+    }
+
+    /// Returns the number of recorded calls of a function
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the function
+    pub fn call_count(&self, name: &str) -> usize {
+        let calls = self.calls.lock().unwrap();
+        calls.iter().filter(|call| call.name == name).count()
+    }
+
+    /// Returns copies of the arguments of every recorded call of a function
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the function
+    pub fn calls_to(&self, name: &str) -> Vec<Vec<Owned>> {
+        let calls = self.calls.lock().unwrap();
+        calls
+            .iter()
+            .filter(|call| call.name == name)
+            .map(|call| call.args.iter().map(|arg| arg.copy()).collect())
+            .collect()
+    }
+
+    /// Takes every recorded call, in the order they were made
+    pub fn take_calls(&self) -> Vec<MockCall> {
+        std::mem::take(&mut *self.calls.lock().unwrap())
+    }
+
+    /// Panics unless a function was called with the given arguments at least once
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the function
+    /// * `args` - the expected arguments
+    #[track_caller]
+    pub fn assert_called_with(&self, name: &str, args: &[Owned]) {
+        let expected: Vec<Vec<u8>> = args.iter().map(|arg| encode(arg)).collect();
+        let calls = self.calls_to(name);
+        let found = calls.iter().any(|call| {
+            call.len() == expected.len()
+                && call
+                    .iter()
+                    .zip(&expected)
+                    .all(|(arg, exp)| encode(arg) == *exp)
+        });
+        if !found {
+            panic!(
+                "expected a call of `{}` with {:?}, found {:?}",
+                name, args, calls
+            );
+        }
+    }
+
+    /// Panics if a function was called a number of times other than expected
+    /// or if a function the mock does not have was called
+    #[track_caller]
+    pub fn verify(&self) {
+        let mut problems = Vec::new();
+        for (name, times) in &self.expected {
+            let count = self.call_count(name);
+            if count != *times {
+                problems.push(format!(
+                    "`{}` was called {} times, expected {}",
+                    name, count, *times
+                ));
+            }
+        }
+        let calls = self.calls.lock().unwrap();
+        for call in calls
+            .iter()
+            .filter(|call| !self.fns.contains_key(&call.name))
+        {
+            problems.push(format!("`{}` was called but is not mocked", call.name));
+        }
+        if !problems.is_empty() {
+            panic!("unmet expectations:\n{}", problems.join("\n"));
+        }
+    }
+}
+
+impl ModuleApi for MockModule {
+    fn functions(&self) -> Vec<FunctionInfo> {
+        self.fns
+            .keys()
+            .map(|name| FunctionInfo::new(name))
+            .collect()
+    }
+
+    fn has_fn(&self, name: &str) -> bool {
+        self.fns.contains_key(name)
+    }
+
+    fn call_with_borrowed(&self, name: &str, args: &[Borrowed<'_>]) -> Option<Owned> {
+        self.calls.lock().unwrap().push(MockCall {
+            name: String::from(name),
+            args: args.iter().map(|arg| arg.copy()).collect(),
+        });
+        self.fns.get(name).map(|f| f(args))
+    }
+}
+
+impl fmt::Debug for MockModule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockModule")
+            .field("functions", &self.fns.keys().collect::<Vec<_>>())
+            .field("expected", &self.expected)
+            .field("calls", &self.calls)
+            .finish()
+    }
+}
@@ -0,0 +1,379 @@
+use crate::callback::CALLBACK_KEY;
+use crate::cancel::CANCEL_KEY;
+use crate::handle::{HANDLE_KEY, OWNED_KEY};
+use crate::value::*;
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::ffi::c_void;
+use std::ptr::{null, null_mut};
+use std::sync::atomic::{AtomicPtr, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+
+/// The name of the symbol making a DLL use the `ObjectTable` of its host
+pub(crate) const ATTACH_SYMBOL: &str = "dy_attach";
+
+/// The kind of a `Callback`
+pub(crate) const KIND_CALLBACK: u32 = 1;
+
+/// The kind of a `CancelToken`
+pub(crate) const KIND_CANCEL: u32 = 2;
+
+/// The kind of a `Handle`
+pub(crate) const KIND_HANDLE: u32 = 3;
+
+/// The kind of a reference of a `Handle` moved into a value, taken over once
+pub(crate) const KIND_HANDLE_TICKET: u32 = 4;
+
+/// The keys marking generic maps as objects, which are only found through the
+/// `ObjectTable` of the process which made them
+const OBJECT_KEYS: &[&str] = &[CALLBACK_KEY, CANCEL_KEY, HANDLE_KEY, OWNED_KEY];
+
+/// The part of every object passed by id, read by every binary of the process
+///
+/// The object follows it in memory; `release` of the binary which made the
+/// object is the only code dropping it.
+#[repr(C)]
+pub(crate) struct ObjectHeader {
+    refs: AtomicUsize,
+    id: AtomicU64,
+    table: AtomicPtr<ObjectTable>,
+    release: unsafe extern "C" fn(header: *const ObjectHeader),
+}
+
+/// Indicates the live objects of a process, shared by a host with its DLLs
+///
+/// Values only carry the ids of objects, so that a forged value names either
+/// no object or an object which is alive, never an arbitrary address.
+#[doc(hidden)]
+#[repr(C)]
+pub struct ObjectTable {
+    insert: unsafe extern "C" fn(kind: u32, header: *const ObjectHeader, owner: u64) -> u64,
+    acquire: unsafe extern "C" fn(kind: u32, id: u64) -> *const ObjectHeader,
+    take: unsafe extern "C" fn(kind: u32, id: u64) -> *const ObjectHeader,
+    remove: unsafe extern "C" fn(id: u64) -> *mut c_void,
+    drop_owner: unsafe extern "C" fn(owner: *mut c_void),
+}
+
+/// Keeps the DLL which made an object loaded while the object is alive
+type Owner = Arc<dyn Any + Send + Sync>;
+
+/// Indicates an object of this binary, or the reference of a handle moved into
+/// a value
+struct Entry {
+    kind: u32,
+    header: usize,
+    owner: Option<Owner>,
+}
+
+/// The objects of this binary, by id
+static ENTRIES: Mutex<BTreeMap<u64, Entry>> = Mutex::new(BTreeMap::new());
+
+/// The DLLs this binary attached, by the token their objects are inserted with
+static OWNERS: Mutex<BTreeMap<u64, Weak<dyn Any + Send + Sync>>> = Mutex::new(BTreeMap::new());
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+static LOCAL_TABLE: ObjectTable = ObjectTable {
+    insert,
+    acquire,
+    take,
+    remove,
+    drop_owner,
+};
+
+/// The table of the host, once it attached this binary
+static HOST_TABLE: AtomicPtr<ObjectTable> = AtomicPtr::new(null_mut());
+
+/// The token the host knows this binary by, 0 until it attached this binary
+static OWNER_TOKEN: AtomicU64 = AtomicU64::new(0);
+
+fn entries() -> MutexGuard<'static, BTreeMap<u64, Entry>> {
+    ENTRIES.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+fn owners() -> MutexGuard<'static, BTreeMap<u64, Weak<dyn Any + Send + Sync>>> {
+    OWNERS.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+unsafe extern "C" fn insert(kind: u32, header: *const ObjectHeader, owner: u64) -> u64 {
+    let owner = owners().get(&owner).and_then(Weak::upgrade);
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let entry = Entry {
+        kind,
+        header: header as usize,
+        owner,
+    };
+    entries().insert(id, entry);
+    id
+}
+
+unsafe extern "C" fn acquire(kind: u32, id: u64) -> *const ObjectHeader {
+    let entries = entries();
+    let header = match entries.get(&id) {
+        Some(entry) if entry.kind == kind => entry.header as *const ObjectHeader,
+        _ => return null(),
+    };
+    // an object whose last reference is being dropped is still in the table;
+    // holding the lock keeps it from being released meanwhile
+    let alive = (*header)
+        .refs
+        .fetch_update(Ordering::AcqRel, Ordering::Acquire, |refs| {
+            Some(refs + 1).filter(|_| refs > 0)
+        })
+        .is_ok();
+    if alive {
+        header
+    } else {
+        null()
+    }
+}
+
+unsafe extern "C" fn take(kind: u32, id: u64) -> *const ObjectHeader {
+    let mut entries = entries();
+    match entries.get(&id) {
+        Some(entry) if entry.kind == kind => {
+            let header = entry.header as *const ObjectHeader;
+            entries.remove(&id);
+            header
+        }
+        _ => null(),
+    }
+}
+
+/// Removes an object, returning what keeps the DLL which made it loaded, to be
+/// passed to `drop_owner` once the object is dropped
+unsafe extern "C" fn remove(id: u64) -> *mut c_void {
+    match entries().remove(&id).and_then(|entry| entry.owner) {
+        Some(owner) => Box::into_raw(Box::new(owner)) as *mut c_void,
+        None => null_mut(),
+    }
+}
+
+unsafe extern "C" fn drop_owner(owner: *mut c_void) {
+    if !owner.is_null() {
+        drop(Box::from_raw(owner as *mut Owner));
+    }
+}
+
+/// Returns the table this binary registers and finds objects in
+fn table() -> &'static ObjectTable {
+    let host = HOST_TABLE.load(Ordering::Acquire);
+    if host.is_null() {
+        &LOCAL_TABLE
+    } else {
+        unsafe { &*host }
+    }
+}
+
+/// Returns the table passed to the DLLs this binary loads
+pub(crate) fn current_table() -> *const ObjectTable {
+    table()
+}
+
+/// Makes this binary use the table of its host
+///
+/// # Safety
+///
+/// `table` must be the table of a binary staying loaded as long as this one.
+///
+/// # Arguments
+///
+/// * `table` - the table of the host
+/// * `owner` - the token the host knows this binary by
+#[doc(hidden)]
+pub unsafe fn __attach_objects(table: *const ObjectTable, owner: u64) {
+    if !table.is_null() {
+        OWNER_TOKEN.store(owner, Ordering::Relaxed);
+        HOST_TABLE.store(table as *mut ObjectTable, Ordering::Release);
+    }
+}
+
+/// Makes the DLL use the `ObjectTable` of its host, so that objects made by
+/// either are found by both
+///
+/// # Safety
+///
+/// `table` must be the table of the host loading the DLL. Functions linked
+/// statically with the `builtin` feature use the table of their binary.
+#[cfg(all(feature = "export", not(feature = "builtin")))]
+#[no_mangle]
+pub unsafe extern "C" fn dy_attach(table: *const ObjectTable, owner: u64) {
+    __attach_objects(table, owner)
+}
+
+/// Shares the library of a DLL among the modules loading it, keeping it
+/// loaded while objects the DLL made are alive
+///
+/// The token is the address of the `dy_attach` function of the DLL, which is
+/// the same for every module loading it and unique in the process. Objects of
+/// DLLs loaded by other DLLs do not keep them loaded.
+///
+/// # Arguments
+///
+/// * `token` - the token the DLL is attached with
+/// * `lib` - the library just loaded, dropped if the DLL is loaded already
+#[cfg(feature = "import")]
+pub(crate) fn share_library<L: Any + Send + Sync>(token: u64, lib: L) -> Arc<L> {
+    let mut owners = owners();
+    owners.retain(|_, owner| owner.strong_count() > 0);
+    if let Some(shared) = owners.get(&token).and_then(Weak::upgrade) {
+        if let Ok(shared) = shared.downcast::<L>() {
+            return shared;
+        }
+    }
+    let lib = Arc::new(lib);
+    let owner: Owner = lib.clone();
+    owners.insert(token, Arc::downgrade(&owner));
+    lib
+}
+
+impl ObjectHeader {
+    /// Makes the header of an object holding one reference, not registered yet
+    ///
+    /// # Arguments
+    ///
+    /// * `release` - the function dropping the object
+    pub(crate) fn new(release: unsafe extern "C" fn(header: *const ObjectHeader)) -> ObjectHeader {
+        ObjectHeader {
+            refs: AtomicUsize::new(1),
+            id: AtomicU64::new(0),
+            table: AtomicPtr::new(null_mut()),
+            release,
+        }
+    }
+
+    /// Returns the id values carry
+    pub(crate) fn id(&self) -> u64 {
+        self.id.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of references
+    pub(crate) fn refs(&self) -> usize {
+        self.refs.load(Ordering::Relaxed)
+    }
+}
+
+/// Registers an object, so that it is found by its id
+///
+/// # Safety
+///
+/// `header` must be the header of a boxed object, holding its only reference.
+///
+/// # Arguments
+///
+/// * `kind` - the kind of the object
+/// * `header` - the header
+pub(crate) unsafe fn register(kind: u32, header: *const ObjectHeader) {
+    let table = table();
+    (*header).table.store(
+        table as *const ObjectTable as *mut ObjectTable,
+        Ordering::Relaxed,
+    );
+    let id = (table.insert)(kind, header, OWNER_TOKEN.load(Ordering::Relaxed));
+    (*header).id.store(id, Ordering::Relaxed);
+}
+
+/// Takes another reference of an object
+///
+/// # Safety
+///
+/// `header` must be the header of an object the caller holds a reference of.
+pub(crate) unsafe fn retain(header: *const ObjectHeader) {
+    (*header).refs.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Drops a reference of an object, dropping the object with the last one
+///
+/// The DLL which made the object may be unloaded afterwards, so a DLL must not
+/// drop the last reference of its own objects on a thread of its own once its
+/// host may have unloaded it.
+///
+/// # Safety
+///
+/// `header` must be the header of an object the caller holds a reference of.
+pub(crate) unsafe fn release(header: *const ObjectHeader) {
+    if (*header).refs.fetch_sub(1, Ordering::AcqRel) == 1 {
+        let table = &*(*header).table.load(Ordering::Relaxed);
+        let owner = (table.remove)((*header).id());
+        ((*header).release)(header);
+        (table.drop_owner)(owner)
+    }
+}
+
+/// Reads the id an object is passed by
+///
+/// # Arguments
+///
+/// * `val` - the generic map passing the object
+/// * `key` - the key holding the id
+pub(crate) fn object_id(val: &Value, key: &str) -> Option<u64> {
+    let id = val.as_map()?.at(key)?.get_val().as_int()?.get();
+    u64::try_from(id).ok()
+}
+
+/// Finds a live object, taking a reference of it
+///
+/// # Arguments
+///
+/// * `kind` - the kind of the object
+/// * `id` - the id of the object
+pub(crate) fn find(kind: u32, id: u64) -> Option<*const ObjectHeader> {
+    let header = unsafe { (table().acquire)(kind, id) };
+    Some(header).filter(|header| !header.is_null())
+}
+
+/// Moves a reference of an object into a new entry, taken over by `take_entry`
+///
+/// # Safety
+///
+/// `header` must be the header of a registered object, whose reference the
+/// caller gives up.
+///
+/// # Arguments
+///
+/// * `kind` - the kind of the entry
+/// * `header` - the header
+pub(crate) unsafe fn insert_entry(kind: u32, header: *const ObjectHeader) -> u64 {
+    // the entry of the object keeps the DLL which made it loaded
+    (table().insert)(kind, header, 0)
+}
+
+/// Removes an entry made by `insert_entry`, taking over its reference
+///
+/// # Arguments
+///
+/// * `kind` - the kind of the entry
+/// * `id` - the id of the entry
+pub(crate) fn take_entry(kind: u32, id: u64) -> Option<*const ObjectHeader> {
+    let header = unsafe { (table().take)(kind, id) };
+    Some(header).filter(|header| !header.is_null())
+}
+
+/// Returns `true` if the key marks generic maps as objects
+///
+/// # Arguments
+///
+/// * `key` - the key
+pub(crate) fn is_object_key(key: &str) -> bool {
+    OBJECT_KEYS.contains(&key)
+}
+
+/// Returns the first key marking an object in a value or in its elements
+///
+/// # Arguments
+///
+/// * `val` - the value
+pub(crate) fn find_object_key(val: &Value) -> Option<&'static str> {
+    match val.as_type() {
+        As::Arr(arr) => arr.iter().find_map(|elem| find_object_key(&elem)),
+        As::Map(map) => map.iter().find_map(|pair| {
+            let key = pair.get_key();
+            match OBJECT_KEYS.iter().find(|object_key| **object_key == key) {
+                Some(object_key) => Some(*object_key),
+                None => find_object_key(pair.get_val()),
+            }
+        }),
+        _ => None,
+    }
+}
@@ -0,0 +1,161 @@
+use crate::error::CallError;
+use crate::value::*;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::Duration;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// The time an idle worker waits for a job before exiting
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The number of worker threads running at most unless `set_max_workers` is called
+pub const DEFAULT_MAX_WORKERS: usize = 64;
+
+/// Runs calls of imported functions off the calling thread
+///
+/// A worker is started whenever every worker is busy, up to the maximum
+/// number of workers; further calls wait in a queue for a worker to be free.
+struct Pool {
+    state: Mutex<State>,
+    ready: Condvar,
+}
+
+struct State {
+    jobs: VecDeque<Job>,
+    idle: usize,
+    workers: usize,
+    max_workers: usize,
+}
+
+fn pool() -> &'static Pool {
+    static POOL: OnceLock<Pool> = OnceLock::new();
+    POOL.get_or_init(|| Pool {
+        state: Mutex::new(State {
+            jobs: VecDeque::new(),
+            idle: 0,
+            workers: 0,
+            max_workers: DEFAULT_MAX_WORKERS,
+        }),
+        ready: Condvar::new(),
+    })
+}
+
+/// Runs a job on a worker thread
+///
+/// # Arguments
+///
+/// * `job` - the job
+pub(crate) fn spawn<F: FnOnce() + Send + 'static>(job: F) {
+    let pool = pool();
+    let mut state = pool.state.lock().unwrap();
+    state.jobs.push_back(Box::new(job));
+    if state.jobs.len() > state.idle && state.workers < state.max_workers {
+        thread::Builder::new()
+            .name(String::from("dy-worker"))
+            .spawn(work)
+            .expect("failed to start a dy worker thread");
+        state.workers += 1;
+    } else {
+        pool.ready.notify_one();
+    }
+}
+
+/// Sets the number of worker threads running calls off the calling thread at
+/// most, `DEFAULT_MAX_WORKERS` by default
+///
+/// Calls made while every worker is busy wait in a queue, so calls which never
+/// return may keep later calls from running; the time a call waits counts
+/// towards its timeout. Lowering the maximum does not stop running workers.
+///
+/// # Arguments
+///
+/// * `max` - the number of workers, at least 1
+pub fn set_max_workers(max: usize) {
+    pool().state.lock().unwrap().max_workers = max.max(1);
+}
+
+fn work() {
+    let pool = pool();
+    loop {
+        let mut state = pool.state.lock().unwrap();
+        let job = loop {
+            if let Some(job) = state.jobs.pop_front() {
+                break job;
+            }
+            state.idle += 1;
+            let (next, timeout) = pool.ready.wait_timeout(state, IDLE_TIMEOUT).unwrap();
+            state = next;
+            state.idle -= 1;
+            if timeout.timed_out() && state.jobs.is_empty() {
+                state.workers -= 1;
+                return;
+            }
+        };
+        drop(state);
+        // jobs report their results through channels; a panic only drops the sender
+        let _ = catch_unwind(AssertUnwindSafe(job));
+    }
+}
+
+/// Indicates the result of a call running on a worker thread
+///
+/// Works with any executor: the call starts as soon as the future is made,
+/// and the task awaiting it is woken when the call returns. Dropping the
+/// future does not stop the call.
+pub struct CallFuture {
+    slot: Arc<Mutex<Slot>>,
+}
+
+#[derive(Default)]
+struct Slot {
+    rtn: Option<Owned>,
+    waker: Option<Waker>,
+}
+
+impl CallFuture {
+    /// Runs a call on a worker thread
+    ///
+    /// # Arguments
+    ///
+    /// * `call` - the call
+    pub(crate) fn spawn<F: FnOnce() -> Owned + Send + 'static>(call: F) -> CallFuture {
+        let slot = Arc::new(Mutex::new(Slot::default()));
+        let done = slot.clone();
+        spawn(move || {
+            let rtn = catch_unwind(AssertUnwindSafe(call)).unwrap_or_else(|_| {
+                CallError::Panic {
+                    message: String::from("the worker thread panicked"),
+                    location: None,
+                }
+                .to_value()
+            });
+            let mut done = done.lock().unwrap();
+            done.rtn = Some(rtn);
+            if let Some(waker) = done.waker.take() {
+                waker.wake();
+            }
+        });
+        CallFuture { slot }
+    }
+}
+
+impl Future for CallFuture {
+    type Output = Owned;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Owned> {
+        let mut slot = self.slot.lock().unwrap();
+        match slot.rtn.take() {
+            Some(rtn) => Poll::Ready(rtn),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
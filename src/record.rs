@@ -0,0 +1,160 @@
+use crate::codec::write_value;
+use crate::convert::{FromValue, IntoArgs};
+use crate::error::{into_result, CallError};
+use crate::hook::CallHook;
+use crate::import::Function;
+use crate::replay::{ARGS_KEY, FUNCTION_KEY, RETURN_KEY};
+use crate::value::*;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Writes calls of exported functions to a file served later by `ReplayModule`
+///
+/// Each call is written as a generic map `{"function": ..., "args": [...],
+/// "return": ...}` in the encoding of `dy::codec`, and flushed right away so
+/// that a crash loses nothing. Clones share the same file.
+///
+/// Wrap a `Function` with `wrap`, or add the recorder to a `Module` with
+/// `Module::add_hook` to record every call made through the module.
+#[derive(Clone)]
+pub struct Recorder {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    out: Mutex<Box<dyn Write + Send>>,
+    error: Mutex<Option<io::Error>>,
+}
+
+/// Indicates an exported function whose calls are written by a `Recorder`
+pub struct RecordingFunction<'lib> {
+    func: Function<'lib>,
+    recorder: Recorder,
+}
+
+impl Recorder {
+    /// Creates a new recorder writing to a file, truncating it
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the path to the file
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Recorder> {
+        Ok(Recorder::from_writer(BufWriter::new(File::create(path)?)))
+    }
+
+    /// Creates a new recorder writing to any writer
+    ///
+    /// # Arguments
+    ///
+    /// * `w` - the writer
+    pub fn from_writer<W: Write + Send + 'static>(w: W) -> Recorder {
+        Recorder {
+            inner: Arc::new(Inner {
+                out: Mutex::new(Box::new(w)),
+                error: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Writes a call
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the function
+    /// * `args` - the arguments
+    /// * `rtn` - the returned value
+    pub fn record(&self, name: &str, args: &[Borrowed<'_>], rtn: &Value) -> io::Result<()> {
+        let entry = Value::new_map(vec![
+            (FUNCTION_KEY, Value::new_str(name)),
+            (
+                ARGS_KEY,
+                Value::new_arr(args.iter().map(|arg| arg.copy()).collect()),
+            ),
+            (RETURN_KEY, rtn.copy()),
+        ]);
+        let mut out = self.inner.out.lock().unwrap();
+        write_value(&mut *out, &entry)?;
+        out.flush()
+    }
+
+    /// Takes the first error which occurred while recording a call made
+    /// through a hook or a `RecordingFunction`
+    pub fn take_error(&self) -> Option<io::Error> {
+        self.inner.error.lock().unwrap().take()
+    }
+
+    /// Wraps a function so that its calls are recorded
+    ///
+    /// # Arguments
+    ///
+    /// * `func` - the function
+    pub fn wrap<'lib>(&self, func: Function<'lib>) -> RecordingFunction<'lib> {
+        RecordingFunction {
+            func,
+            recorder: self.clone(),
+        }
+    }
+
+    fn record_or_keep_error(&self, name: &str, args: &[Borrowed<'_>], rtn: &Value) {
+        if let Err(err) = self.record(name, args, rtn) {
+            self.inner.error.lock().unwrap().get_or_insert(err);
+        }
+    }
+}
+
+impl CallHook for Recorder {
+    fn after(&self, name: &str, args: &[Borrowed<'_>], rtn: &Value, _elapsed: Duration) {
+        self.record_or_keep_error(name, args, rtn);
+    }
+}
+
+impl<'lib> RecordingFunction<'lib> {
+    /// Returns the name of the function
+    pub fn name(&self) -> &str {
+        self.func.name()
+    }
+
+    /// Invokes the exported function
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments
+    pub fn call_with_borrowed(&self, args: &[Borrowed<'_>]) -> Owned {
+        let rtn = self.func.call_with_borrowed(args);
+        self.recorder.record_or_keep_error(self.name(), args, &rtn);
+        rtn
+    }
+
+    /// Calls the exported function and disposes arguments after the invocation
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments
+    pub fn call(&self, args: Vec<Owned>) -> Owned {
+        let borrowed: Vec<Borrowed<'_>> = args.iter().map(|arg| arg.borrow()).collect();
+        self.call_with_borrowed(&borrowed)
+    }
+
+    /// Calls the exported function and disposes arguments after the invocation,
+    /// reporting a panic or an error of the function as `CallError`
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments
+    pub fn try_call(&self, args: Vec<Owned>) -> Result<Owned, CallError> {
+        into_result(self.call(args))
+    }
+
+    /// Calls the exported function with Rust types, converting the result back
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments, usually a tuple
+    pub fn call_typed<Args: IntoArgs, Ret: FromValue>(&self, args: Args) -> Result<Ret, CallError> {
+        let rtn = self.try_call(args.into_args())?;
+        Ret::from_value(&rtn).map_err(CallError::unexpected_return)
+    }
+}
@@ -0,0 +1,329 @@
+//! Bookkeeping for hosts loading many DLLs
+//!
+//! A `PluginRegistry` loads DLLs under a name and exposes their functions as
+//! `module.function`.
+
+use crate::error::CallError;
+use crate::loader::{LoadError, ModuleLoader};
+use crate::manifest::FunctionInfo;
+use crate::shared::{OwnedFunction, SharedModule};
+use crate::value::*;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Indicates the reason why a registry operation failed
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegistryError {
+    /// The DLL could not be loaded
+    Load(LoadError),
+    /// A DLL is already registered under the name
+    Collision(String),
+    /// No DLL is registered under the name
+    UnknownModule(String),
+    /// The DLL does not export the function
+    UnknownFunction(String),
+    /// The DLL is disabled
+    Disabled(String),
+    /// The name is not of the form `module.function`
+    InvalidName(String),
+    /// The function failed
+    Call(CallError),
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::Load(err) => write!(f, "{}", err),
+            RegistryError::Collision(name) => {
+                write!(f, "a module named `{}` is already registered", name)
+            }
+            RegistryError::UnknownModule(name) => write!(f, "no module named `{}`", name),
+            RegistryError::UnknownFunction(name) => write!(f, "no function named `{}`", name),
+            RegistryError::Disabled(name) => write!(f, "the module `{}` is disabled", name),
+            RegistryError::InvalidName(name) => {
+                write!(f, "`{}` is not of the form `module.function`", name)
+            }
+            RegistryError::Call(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for RegistryError {}
+
+impl From<LoadError> for RegistryError {
+    fn from(err: LoadError) -> RegistryError {
+        RegistryError::Load(err)
+    }
+}
+
+impl From<CallError> for RegistryError {
+    fn from(err: CallError) -> RegistryError {
+        RegistryError::Call(err)
+    }
+}
+
+/// Reports the outcome of `PluginRegistry::scan`
+#[derive(Debug, Default)]
+pub struct ScanReport {
+    /// the names of the DLLs registered
+    pub loaded: Vec<String>,
+    /// the files which looked like DLLs but could not be registered
+    pub failed: Vec<(PathBuf, RegistryError)>,
+}
+
+struct Plugin {
+    module: SharedModule,
+    enabled: bool,
+    infos: Vec<FunctionInfo>,
+    functions: BTreeMap<String, OwnedFunction>,
+}
+
+/// Loads DLLs under names and dispatches calls of `module.function`
+///
+/// Functions listed in the manifest of a DLL are resolved when it is
+/// registered; other functions are resolved when called.
+pub struct PluginRegistry {
+    loader: ModuleLoader,
+    plugins: BTreeMap<String, Plugin>,
+}
+
+/// Splits `module.function` at the first dot
+fn split_name(name: &str) -> Result<(&str, &str), RegistryError> {
+    let mut parts = name.splitn(2, '.');
+    match (parts.next(), parts.next()) {
+        (Some(module), Some(function)) if !module.is_empty() && !function.is_empty() => {
+            Ok((module, function))
+        }
+        _ => Err(RegistryError::InvalidName(String::from(name))),
+    }
+}
+
+impl PluginRegistry {
+    /// Creates a new, empty registry loading DLLs with the default `ModuleLoader`
+    pub fn new() -> PluginRegistry {
+        PluginRegistry::with_loader(ModuleLoader::new())
+    }
+
+    /// Creates a new, empty registry
+    ///
+    /// # Arguments
+    ///
+    /// * `loader` - the loader used by `load` and whose naming scheme is used by `scan`
+    pub fn with_loader(loader: ModuleLoader) -> PluginRegistry {
+        PluginRegistry {
+            loader,
+            plugins: BTreeMap::new(),
+        }
+    }
+
+    /// Registers every DLL in a directory, named after its file
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - the directory to scan
+    pub fn scan<P: AsRef<Path>>(&mut self, dir: P) -> std::io::Result<ScanReport> {
+        let mut candidates: Vec<(String, PathBuf)> = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if !path.is_file() {
+                continue;
+            }
+            let name = path
+                .file_name()
+                .and_then(|file_name| file_name.to_str())
+                .and_then(|file_name| self.loader.naming_scheme().module_name(file_name));
+            if let Some(name) = name {
+                candidates.push((name, path));
+            }
+        }
+        candidates.sort();
+
+        let mut report = ScanReport::default();
+        for (name, path) in candidates {
+            let rtn = self
+                .loader
+                .open(&path)
+                .map_err(RegistryError::from)
+                .and_then(|module| self.add(&name, module));
+            match rtn {
+                Ok(()) => report.loaded.push(name),
+                Err(err) => report.failed.push((path, err)),
+            }
+        }
+        Ok(report)
+    }
+
+    /// Loads a DLL with the loader of the registry and registers it under its name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the DLL
+    pub fn load(&mut self, name: &str) -> Result<(), RegistryError> {
+        if self.plugins.contains_key(name) {
+            return Err(RegistryError::Collision(String::from(name)));
+        }
+        let module = self.loader.load(name)?;
+        self.add(name, module)
+    }
+
+    /// Registers a loaded DLL
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name to register the DLL under; must not contain `.`
+    /// * `module` - the DLL
+    pub fn add<M: Into<SharedModule>>(
+        &mut self,
+        name: &str,
+        module: M,
+    ) -> Result<(), RegistryError> {
+        if name.is_empty() || name.contains('.') {
+            return Err(RegistryError::InvalidName(String::from(name)));
+        }
+        if self.plugins.contains_key(name) {
+            return Err(RegistryError::Collision(String::from(name)));
+        }
+
+        let module = module.into();
+        let infos = module.functions();
+        let mut functions = BTreeMap::new();
+        for info in infos.iter() {
+            let func = module
+                .get_fn(&info.name)
+                .ok_or_else(|| RegistryError::UnknownFunction(format!("{}.{}", name, info.name)))?;
+            if functions.insert(info.name.clone(), func).is_some() {
+                return Err(RegistryError::Collision(format!("{}.{}", name, info.name)));
+            }
+        }
+
+        self.plugins.insert(
+            String::from(name),
+            Plugin {
+                module,
+                enabled: true,
+                infos,
+                functions,
+            },
+        );
+        Ok(())
+    }
+
+    /// Unregisters a DLL; it is unloaded once no `OwnedFunction` of it is left
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the DLL
+    pub fn unload(&mut self, name: &str) -> Result<(), RegistryError> {
+        match self.plugins.remove(name) {
+            Some(_) => Ok(()),
+            None => Err(RegistryError::UnknownModule(String::from(name))),
+        }
+    }
+
+    /// Makes the functions of a DLL callable again
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the DLL
+    pub fn enable(&mut self, name: &str) -> Result<(), RegistryError> {
+        self.set_enabled(name, true)
+    }
+
+    /// Keeps a DLL loaded but refuses calls of its functions
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the DLL
+    pub fn disable(&mut self, name: &str) -> Result<(), RegistryError> {
+        self.set_enabled(name, false)
+    }
+
+    fn set_enabled(&mut self, name: &str, enabled: bool) -> Result<(), RegistryError> {
+        match self.plugins.get_mut(name) {
+            Some(plugin) => {
+                plugin.enabled = enabled;
+                Ok(())
+            }
+            None => Err(RegistryError::UnknownModule(String::from(name))),
+        }
+    }
+
+    /// Returns whether a DLL is registered and enabled
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the DLL
+    pub fn is_enabled(&self, name: &str) -> bool {
+        matches!(self.plugins.get(name), Some(plugin) if plugin.enabled)
+    }
+
+    /// Returns the names of the registered DLLs
+    pub fn modules(&self) -> Vec<&str> {
+        self.plugins.keys().map(|name| name.as_str()).collect()
+    }
+
+    /// Returns the registered DLL with the given name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the DLL
+    pub fn get_module(&self, name: &str) -> Option<&SharedModule> {
+        self.plugins.get(name).map(|plugin| &plugin.module)
+    }
+
+    /// Lists the manifest entries of the enabled DLLs, named `module.function`
+    pub fn functions(&self) -> Vec<FunctionInfo> {
+        let mut rtn = Vec::new();
+        for (name, plugin) in self.plugins.iter().filter(|(_, plugin)| plugin.enabled) {
+            for info in plugin.infos.iter() {
+                rtn.push(FunctionInfo {
+                    name: format!("{}.{}", name, info.name),
+                    ..info.clone()
+                });
+            }
+        }
+        rtn
+    }
+
+    /// Retrieves a function of an enabled DLL
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the function, `module.function`
+    pub fn get_fn(&self, name: &str) -> Result<OwnedFunction, RegistryError> {
+        let (module, function) = split_name(name)?;
+        let plugin = self
+            .plugins
+            .get(module)
+            .ok_or_else(|| RegistryError::UnknownModule(String::from(module)))?;
+        if !plugin.enabled {
+            return Err(RegistryError::Disabled(String::from(module)));
+        }
+        match plugin.functions.get(function) {
+            Some(func) => Ok(func.clone()),
+            None => plugin
+                .module
+                .get_fn(function)
+                .ok_or_else(|| RegistryError::UnknownFunction(String::from(name))),
+        }
+    }
+
+    /// Calls a function of an enabled DLL and disposes arguments after the invocation
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the function, `module.function`
+    /// * `args` - the arguments
+    pub fn call(&self, name: &str, args: Vec<Owned>) -> Result<Owned, RegistryError> {
+        Ok(self.get_fn(name)?.try_call(args)?)
+    }
+}
+
+impl Default for PluginRegistry {
+    fn default() -> PluginRegistry {
+        PluginRegistry::new()
+    }
+}
@@ -0,0 +1,428 @@
+use crate::cancel::CancelToken;
+use crate::convert::{FromValue, IntoArgs};
+use crate::error::{into_result, CallError};
+use crate::loader::{LoadError, ModuleLoader};
+use crate::pool::CallFuture;
+use crate::shared::{OwnedFunction, SharedModule};
+use crate::value::*;
+use std::collections::BTreeSet;
+use std::env::temp_dir;
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+type ReloadCallback = Box<dyn Fn(&SharedModule) + Send + Sync>;
+type ErrorCallback = Box<dyn Fn(&LoadError) + Send + Sync>;
+
+/// Indicates a DLL which is reloaded when its file changes
+///
+/// Every version is loaded from a private copy of the file, so that the new
+/// version can be loaded while the old one is still in use. Calls started
+/// before a reload finish on the old version, which is unloaded once the last
+/// of them completes.
+#[derive(Clone)]
+pub struct ReloadableModule {
+    inner: Arc<Inner>,
+}
+
+/// Indicates an exported function of a `ReloadableModule`, always calling the latest version
+#[derive(Clone)]
+pub struct ReloadableFunction {
+    module: ReloadableModule,
+    name: String,
+    cached: Arc<Mutex<(usize, OwnedFunction)>>,
+}
+
+/// Polls a `ReloadableModule` on a background thread until dropped
+pub struct Watcher {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+struct Inner {
+    path: PathBuf,
+    loader: ModuleLoader,
+    current: RwLock<Generation>,
+    functions: Mutex<BTreeSet<String>>,
+    config: Mutex<Option<SharedValue>>,
+    on_reload: Mutex<Vec<ReloadCallback>>,
+    on_error: Mutex<Vec<ErrorCallback>>,
+}
+
+struct Generation {
+    module: SharedModule,
+    modified: Option<SystemTime>,
+    version: usize,
+}
+
+static SHADOW_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+/// Loads a private copy of the DLL so that the original file can be replaced freely
+fn load_shadow(loader: &ModuleLoader, path: &Path) -> Result<Generation, LoadError> {
+    let io_error = |err: std::io::Error| LoadError::Open {
+        path: path.to_path_buf(),
+        message: err.to_string(),
+    };
+
+    let modified = modified(path);
+    let file_name = path.file_name().ok_or_else(|| LoadError::Open {
+        path: path.to_path_buf(),
+        message: String::from("not a file"),
+    })?;
+    let shadow = temp_dir().join(format!(
+        "dy-reload-{}-{}-{}",
+        std::process::id(),
+        SHADOW_COUNT.fetch_add(1, Ordering::SeqCst),
+        file_name.to_string_lossy()
+    ));
+    fs::copy(path, &shadow).map_err(io_error)?;
+
+    let module = loader.open(&shadow);
+    // the mapping stays valid after removal on unix; elsewhere the copy is left behind
+    let _ = fs::remove_file(&shadow);
+    Ok(Generation {
+        module: SharedModule::new(module?),
+        modified,
+        version: 0,
+    })
+}
+
+impl ReloadableModule {
+    /// Loads the DLL at the given path
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the path of the DLL
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<ReloadableModule, LoadError> {
+        ReloadableModule::with_loader(path, &ModuleLoader::new())
+    }
+
+    /// Loads the DLL at the given path, applying the policies of `loader`
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the path of the DLL
+    /// * `loader` - the loader whose policies, e.g. `allow_unversioned`, are applied
+    pub fn with_loader<P: AsRef<Path>>(
+        path: P,
+        loader: &ModuleLoader,
+    ) -> Result<ReloadableModule, LoadError> {
+        let path = path.as_ref().to_path_buf();
+        let current = load_shadow(loader, &path)?;
+        Ok(ReloadableModule {
+            inner: Arc::new(Inner {
+                path,
+                loader: loader.clone(),
+                current: RwLock::new(current),
+                functions: Mutex::new(BTreeSet::new()),
+                config: Mutex::new(None),
+                on_reload: Mutex::new(Vec::new()),
+                on_error: Mutex::new(Vec::new()),
+            }),
+        })
+    }
+
+    /// Returns the path of the DLL
+    pub fn path(&self) -> &Path {
+        &self.inner.path
+    }
+
+    /// Returns the number of times the DLL has been reloaded
+    pub fn version(&self) -> usize {
+        self.inner.current.read().unwrap().version
+    }
+
+    /// Returns the current version of the DLL
+    pub fn current(&self) -> SharedModule {
+        self.inner.current.read().unwrap().module.clone()
+    }
+
+    /// Initializes the current version of the DLL, see `Module::initialize`
+    ///
+    /// The configuration is kept, and every version loaded afterwards is
+    /// initialized with it before it is swapped in; a reload is refused if
+    /// the initialization fails.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - the configuration passed to the `#[dy::init]` function
+    pub fn initialize(&self, config: &Value) -> Result<(), CallError> {
+        // a reload holds the write lock until the new version is swapped in
+        let generation = self.inner.current.read().unwrap();
+        let mut stored = self.inner.config.lock().unwrap();
+        generation.module.initialize(config)?;
+        *stored = Some(SharedValue::new(config.copy()));
+        Ok(())
+    }
+
+    /// Retrieves an exported function, following reloads
+    ///
+    /// Once retrieved, a reload is refused if the new version does not export the function.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the function
+    pub fn get_fn(&self, name: &str) -> Option<ReloadableFunction> {
+        let generation = self.inner.current.read().unwrap();
+        let func = generation.module.get_fn(name)?;
+        self.inner
+            .functions
+            .lock()
+            .unwrap()
+            .insert(String::from(name));
+        Some(ReloadableFunction {
+            module: self.clone(),
+            name: String::from(name),
+            cached: Arc::new(Mutex::new((generation.version, func))),
+        })
+    }
+
+    /// Registers a callback invoked with the new version after every successful reload
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - the callback
+    pub fn on_reload<F: Fn(&SharedModule) + Send + Sync + 'static>(&self, f: F) {
+        self.inner.on_reload.lock().unwrap().push(Box::new(f));
+    }
+
+    /// Registers a callback invoked whenever a reload fails; the old version stays in use
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - the callback
+    pub fn on_error<F: Fn(&LoadError) + Send + Sync + 'static>(&self, f: F) {
+        self.inner.on_error.lock().unwrap().push(Box::new(f));
+    }
+
+    /// Reloads the DLL if its file has been modified since it was loaded
+    ///
+    /// Returns whether the DLL has been reloaded.
+    pub fn check(&self) -> Result<bool, LoadError> {
+        let modified = modified(&self.inner.path);
+        if modified.is_none() || modified == self.inner.current.read().unwrap().modified {
+            return Ok(false);
+        }
+        self.reload().map(|_| true)
+    }
+
+    /// Loads the DLL again and swaps it in if it exports every function in use
+    ///
+    /// `get_fn`, `initialize` and calls of functions retrieved before wait
+    /// while the new version is checked and initialized.
+    pub fn reload(&self) -> Result<(), LoadError> {
+        match self.try_reload() {
+            Ok(module) => {
+                for callback in self.inner.on_reload.lock().unwrap().iter() {
+                    callback(&module);
+                }
+                Ok(())
+            }
+            Err(err) => {
+                for callback in self.inner.on_error.lock().unwrap().iter() {
+                    callback(&err);
+                }
+                Err(err)
+            }
+        }
+    }
+
+    fn try_reload(&self) -> Result<SharedModule, LoadError> {
+        let mut generation = match load_shadow(&self.inner.loader, &self.inner.path) {
+            Ok(generation) => generation,
+            Err(err) => {
+                // do not try the same file again
+                self.inner.current.write().unwrap().modified = modified(&self.inner.path);
+                return Err(err);
+            }
+        };
+
+        // no function is retrieved and no configuration is set until the
+        // new version is swapped in or refused
+        let mut current = self.inner.current.write().unwrap();
+        let missing: Vec<String> = self
+            .inner
+            .functions
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|name| generation.module.get_fn(name).is_none())
+            .cloned()
+            .collect();
+        if !missing.is_empty() {
+            // do not try the same file again
+            current.modified = generation.modified;
+            return Err(LoadError::MissingFunctions(missing));
+        }
+
+        if let Some(config) = &*self.inner.config.lock().unwrap() {
+            if let Err(err) = generation.module.initialize(config) {
+                // do not try the same file again
+                current.modified = generation.modified;
+                return Err(LoadError::Initialize {
+                    path: self.inner.path.clone(),
+                    message: String::from(err.message()),
+                });
+            }
+        }
+
+        let module = generation.module.clone();
+        generation.version = current.version + 1;
+        *current = generation;
+        Ok(module)
+    }
+
+    /// Starts polling the file of the DLL on a background thread
+    ///
+    /// Failures are reported to the `on_error` callbacks. Polling stops when
+    /// the returned `Watcher` is dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - the time between two polls
+    pub fn watch(&self, interval: Duration) -> Watcher {
+        let (stop, stopped) = channel();
+        let module = self.clone();
+        let thread = thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                let _ = module.check();
+            }
+        });
+        Watcher {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+}
+
+impl ReloadableFunction {
+    /// Returns the name of the function
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the function of the current version of the DLL
+    ///
+    /// Reloads are refused if the new version lacks the function; should it
+    /// be missing anyway, the last version found exporting it is kept.
+    pub fn current(&self) -> OwnedFunction {
+        let generation = self.module.inner.current.read().unwrap();
+        let mut cached = self.cached.lock().unwrap();
+        if cached.0 != generation.version {
+            if let Some(func) = generation.module.get_fn(&self.name) {
+                *cached = (generation.version, func);
+            }
+        }
+        cached.1.clone()
+    }
+
+    /// Invokes the exported function
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments
+    pub fn call_with_borrowed(&self, args: &[Borrowed<'_>]) -> Owned {
+        self.current().call_with_borrowed(args)
+    }
+
+    /// Calls the exported function and disposes arguments after the invocation
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments
+    pub fn call(&self, args: Vec<Owned>) -> Owned {
+        self.current().call(args)
+    }
+
+    /// Calls the exported function and disposes arguments after the invocation,
+    /// reporting a panic or an error of the function as `CallError`
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments
+    pub fn try_call(&self, args: Vec<Owned>) -> Result<Owned, CallError> {
+        self.current().try_call(args)
+    }
+
+    /// Calls the exported function with Rust types, converting the result back
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments, usually a tuple
+    pub fn call_typed<Args: IntoArgs, Ret: FromValue>(&self, args: Args) -> Result<Ret, CallError> {
+        self.current().call_typed(args)
+    }
+
+    /// Calls the exported function of the current version on a worker thread,
+    /// resolving to its result
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments
+    pub fn call_async(&self, args: Vec<Owned>) -> CallFuture {
+        self.current().call_async(args)
+    }
+
+    /// Calls the exported function of the current version on a worker thread, resolving to
+    /// its result or the panic or the error of the function as `CallError`
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments
+    pub fn try_call_async(
+        &self,
+        args: Vec<Owned>,
+    ) -> impl Future<Output = Result<Owned, CallError>> + Send {
+        let rtn = self.call_async(args);
+        async move { into_result(rtn.await) }
+    }
+
+    /// Calls the exported function on a worker thread, giving up after `timeout`
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments
+    /// * `timeout` - the time to wait for the result
+    pub fn call_with_timeout(
+        &self,
+        args: Vec<Owned>,
+        timeout: Duration,
+    ) -> Result<Owned, CallError> {
+        self.current().call_with_timeout(args, timeout)
+    }
+
+    /// Calls an exported function taking a `CancelToken` as its last parameter on a worker
+    /// thread, cancelling the token if the call does not return within `timeout`
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments, without the token
+    /// * `token` - the token passed to the function
+    /// * `timeout` - the time to wait for the result
+    pub fn call_cancellable(
+        &self,
+        args: Vec<Owned>,
+        token: &CancelToken,
+        timeout: Duration,
+    ) -> Result<Owned, CallError> {
+        self.current().call_cancellable(args, token, timeout)
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
@@ -0,0 +1,780 @@
+//! Isolation of DLLs in a child process
+//!
+//! A `RemoteModule` loads a DLL in a `dy-host` process and forwards calls to
+//! it, so that a crashing DLL cannot take the host application down. On Unix
+//! the two processes talk over a pair of connected Unix sockets, one end of
+//! which is the standard input of `dy-host`; elsewhere over pipes to its
+//! standard input and output, so DLLs must not write to their standard output
+//! there.
+//!
+//! Both directions are sequences of values encoded by `dy::codec`. The first
+//! request opens the DLL and is answered with null or a load error; every
+//! following request is a generic array answered with one value:
+//!
+//! * `["functions"]` - the manifest of the DLL
+//! * `["has_fn", name]` - whether the DLL exports the function
+//! * `["call", name, [args...]]` - the return value of the function, or
+//!   `{"$dy_unknown_function": name}` if the DLL does not export it
+
+use crate::abi::AbiVersion;
+use crate::api::ModuleApi;
+use crate::codec::{read_value, write_value};
+use crate::convert::{FromValue, IntoArgs, IntoValue};
+use crate::error::{into_result, CallError};
+use crate::import::Module;
+use crate::loader::{LoadError, ModuleLoader};
+use crate::manifest::FunctionInfo;
+use crate::object::find_object_key;
+use crate::pool::CallFuture;
+use crate::value::*;
+use std::collections::BTreeMap;
+use std::env::{self, consts::EXE_SUFFIX};
+use std::future::Future;
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The environment variable holding the path of the `dy-host` binary
+pub const HOST_VAR: &str = "DY_HOST";
+
+/// The key of the map `dy-host` answers a call of a function the DLL does not export with
+const UNKNOWN_KEY: &str = "$dy_unknown_function";
+
+/// The time `dy-host` is given to exit once its connection is closed
+const EXIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Indicates a DLL loaded in a `dy-host` process
+///
+/// Mirrors `SharedModule`: clones share the process, and calls from several
+/// threads are forwarded one at a time. If the process crashes, or does not
+/// answer within the timeout set with `set_timeout`, the request in flight
+/// fails with a `CallError` and the next request restarts it.
+#[derive(Clone)]
+pub struct RemoteModule {
+    inner: Arc<Inner>,
+}
+
+/// Indicates an exported function of a `RemoteModule`
+///
+/// Mirrors `OwnedFunction`; the arguments and the return value are copied
+/// between the processes.
+#[derive(Clone)]
+pub struct RemoteFunction {
+    module: RemoteModule,
+    name: String,
+}
+
+/// Indicates how `dy-host` finds the DLL
+enum Target {
+    Open(PathBuf),
+    Load {
+        name: String,
+        candidates: Vec<PathBuf>,
+    },
+}
+
+struct Inner {
+    host: PathBuf,
+    target: Target,
+    allow_unversioned: bool,
+    conn: Mutex<Option<Connection>>,
+    restarts: AtomicUsize,
+    timeout: Mutex<Option<Duration>>,
+}
+
+struct Connection {
+    child: Child,
+    answers: Receiver<io::Result<Owned>>,
+    writer: Option<BufWriter<Box<dyn Write + Send>>>,
+}
+
+/// Finds `dy-host`: `DY_HOST`, next to the current executable or in its parent directory,
+/// then the search of the OS
+fn host_path() -> PathBuf {
+    if let Some(path) = env::var_os(HOST_VAR) {
+        return PathBuf::from(path);
+    }
+    let file_name = format!("dy-host{}", EXE_SUFFIX);
+    if let Ok(exe) = env::current_exe() {
+        // tests and examples live in a subdirectory of the binaries
+        for dir in exe.ancestors().skip(1).take(2) {
+            let path = dir.join(&file_name);
+            if path.is_file() {
+                return path;
+            }
+        }
+    }
+    PathBuf::from(file_name)
+}
+
+fn path_value(path: &Path) -> Owned {
+    Value::new_str(&path.to_string_lossy())
+}
+
+fn abi_version_to_value(v: &AbiVersion) -> Owned {
+    (
+        v.header_version,
+        v.pointer_width,
+        v.keyval_size,
+        v.allocator,
+    )
+        .into_value()
+}
+
+fn abi_version_from_value(val: &Value) -> Option<AbiVersion> {
+    let (header_version, pointer_width, keyval_size, allocator) =
+        <(u32, u32, u32, u32)>::from_value(val).ok()?;
+    Some(AbiVersion {
+        header_version,
+        pointer_width,
+        keyval_size,
+        allocator,
+    })
+}
+
+/// Makes the value `dy-host` reports a load error with
+fn load_error_to_value(err: &LoadError) -> Owned {
+    let mut entries = Vec::new();
+    match err {
+        LoadError::NotFound { name, tried } => {
+            entries.push(("load_error", Value::new_str("not_found")));
+            entries.push(("name", Value::new_str(name)));
+            let tried = tried.iter().map(|path| path_value(path)).collect();
+            entries.push(("tried", Value::new_arr(tried)));
+        }
+        LoadError::Open { path, message } => {
+            entries.push(("load_error", Value::new_str("open")));
+            entries.push(("path", path_value(path)));
+            entries.push(("message", Value::new_str(message)));
+        }
+        LoadError::MissingAbiVersion { path } => {
+            entries.push(("load_error", Value::new_str("missing_abi_version")));
+            entries.push(("path", path_value(path)));
+        }
+        LoadError::AbiMismatch {
+            path,
+            expected,
+            found,
+        } => {
+            entries.push(("load_error", Value::new_str("abi_mismatch")));
+            entries.push(("path", path_value(path)));
+            entries.push(("expected", abi_version_to_value(expected)));
+            entries.push(("found", abi_version_to_value(found)));
+        }
+        LoadError::MissingFunctions(names) => {
+            entries.push(("load_error", Value::new_str("missing_functions")));
+            entries.push(("names", names.clone().into_value()));
+        }
+        LoadError::Initialize { path, message } => {
+            entries.push(("load_error", Value::new_str("initialize")));
+            entries.push(("path", path_value(path)));
+            entries.push(("message", Value::new_str(message)));
+        }
+        LoadError::DuplicateFunctions(names) => {
+            entries.push(("load_error", Value::new_str("duplicate_functions")));
+            entries.push(("names", names.clone().into_value()));
+        }
+    }
+    Value::new_map(entries)
+}
+
+/// Reads a value made by `load_error_to_value`, returning `None` for any other value
+fn load_error_from_value(val: &Value) -> Option<LoadError> {
+    let entries = BTreeMap::<String, Owned>::from_value(val).ok()?;
+    let get_str = |key: &str| String::from_value(entries.get(key)?).ok();
+    let get_path = |key: &str| get_str(key).map(PathBuf::from);
+    let get_strs = |key: &str| Vec::<String>::from_value(entries.get(key)?).ok();
+    let get_abi_version = |key: &str| abi_version_from_value(entries.get(key)?);
+    match get_str("load_error")?.as_str() {
+        "not_found" => Some(LoadError::NotFound {
+            name: get_str("name")?,
+            tried: get_strs("tried")?.into_iter().map(PathBuf::from).collect(),
+        }),
+        "open" => Some(LoadError::Open {
+            path: get_path("path")?,
+            message: get_str("message")?,
+        }),
+        "missing_abi_version" => Some(LoadError::MissingAbiVersion {
+            path: get_path("path")?,
+        }),
+        "abi_mismatch" => Some(LoadError::AbiMismatch {
+            path: get_path("path")?,
+            expected: get_abi_version("expected")?,
+            found: get_abi_version("found")?,
+        }),
+        "missing_functions" => Some(LoadError::MissingFunctions(get_strs("names")?)),
+        "initialize" => Some(LoadError::Initialize {
+            path: get_path("path")?,
+            message: get_str("message")?,
+        }),
+        "duplicate_functions" => Some(LoadError::DuplicateFunctions(get_strs("names")?)),
+        _ => None,
+    }
+}
+
+impl Connection {
+    /// Starts `dy-host` talking over a socket pair passed as its standard input
+    #[cfg(unix)]
+    fn spawn(host: &Path) -> io::Result<Connection> {
+        use std::os::unix::io::OwnedFd;
+        use std::os::unix::net::UnixStream;
+
+        let (stream, remote) = UnixStream::pair()?;
+        let child = Command::new(host)
+            .arg("--stdin-socket")
+            .stdin(Stdio::from(OwnedFd::from(remote)))
+            .spawn()?;
+        let reader = stream.try_clone()?;
+        Ok(Connection::new(child, Box::new(reader), Box::new(stream)))
+    }
+
+    /// Starts `dy-host` talking over its standard input and output
+    #[cfg(not(unix))]
+    fn spawn(host: &Path) -> io::Result<Connection> {
+        let mut child = Command::new(host)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let writer = child.stdin.take().unwrap();
+        let reader = child.stdout.take().unwrap();
+        Ok(Connection::new(child, Box::new(reader), Box::new(writer)))
+    }
+
+    /// Reads the answers of `dy-host` on a thread of their own, so that waiting
+    /// for one can time out
+    fn new(child: Child, reader: Box<dyn Read + Send>, writer: Box<dyn Write + Send>) -> Self {
+        let (sender, answers) = channel();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(reader);
+            loop {
+                let answer = read_value(&mut reader);
+                let failed = answer.is_err();
+                if sender.send(answer).is_err() || failed {
+                    return;
+                }
+            }
+        });
+        Connection {
+            child,
+            answers,
+            writer: Some(BufWriter::new(writer)),
+        }
+    }
+
+    /// Sends a request and waits for its answer
+    ///
+    /// Fails with `ErrorKind::TimedOut` if no answer comes within the timeout.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - the request
+    /// * `timeout` - the time to wait for the answer, `None` to wait forever
+    fn request(&mut self, req: &Value, timeout: Option<Duration>) -> io::Result<Owned> {
+        let closed = || io::Error::new(ErrorKind::BrokenPipe, "closed");
+        let writer = match self.writer.as_mut() {
+            Some(writer) => writer,
+            None => return Err(closed()),
+        };
+        write_value(writer, req)?;
+        writer.flush()?;
+        match timeout {
+            Some(timeout) => match self.answers.recv_timeout(timeout) {
+                Ok(answer) => answer,
+                Err(RecvTimeoutError::Timeout) => {
+                    Err(io::Error::new(ErrorKind::TimedOut, "timed out"))
+                }
+                Err(RecvTimeoutError::Disconnected) => Err(closed()),
+            },
+            None => self.answers.recv().unwrap_or_else(|_| Err(closed())),
+        }
+    }
+
+    /// Closes the connection and waits for `dy-host` to exit, killing it if it does not
+    fn close(&mut self) -> Option<ExitStatus> {
+        drop(self.writer.take());
+        let start = Instant::now();
+        loop {
+            match self.child.try_wait() {
+                Ok(Some(status)) => return Some(status),
+                Ok(None) if start.elapsed() < EXIT_TIMEOUT => {
+                    thread::sleep(Duration::from_millis(5))
+                }
+                Ok(None) => {
+                    let _ = self.child.kill();
+                    return self.child.wait().ok();
+                }
+                Err(_) => return None,
+            }
+        }
+    }
+
+    /// Closes the connection after a failed request, describing what happened
+    ///
+    /// # Arguments
+    ///
+    /// * `err` - the error the request failed with
+    fn fail(mut self, err: io::Error) -> String {
+        match self.close() {
+            Some(status) if !status.success() => format!("the module host crashed: {}", status),
+            _ => format!("the connection to the module host failed: {}", err),
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+impl Inner {
+    /// Starts `dy-host` and lets it load the DLL
+    fn connect(&self) -> Result<Connection, LoadError> {
+        let io_error = |err: io::Error| LoadError::Open {
+            path: self.host.clone(),
+            message: format!("could not start the module host: {}", err),
+        };
+
+        let req = match &self.target {
+            Target::Open(path) => Value::new_arr(vec![
+                Value::new_str("open"),
+                path_value(path),
+                Value::new_bool(self.allow_unversioned),
+            ]),
+            Target::Load { name, candidates } => Value::new_arr(vec![
+                Value::new_str("load"),
+                Value::new_str(name),
+                Value::new_arr(candidates.iter().map(|path| path_value(path)).collect()),
+                Value::new_bool(self.allow_unversioned),
+            ]),
+        };
+        let mut conn = Connection::spawn(&self.host).map_err(io_error)?;
+        let rtn = conn.request(&req, None).map_err(io_error)?;
+        match load_error_from_value(&rtn) {
+            Some(err) => Err(err),
+            None => Ok(conn),
+        }
+    }
+
+    /// Forwards a request, restarting `dy-host` if it has crashed before
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - the request
+    fn request(&self, req: &Value) -> Result<Owned, CallError> {
+        let mut conn = self.conn.lock().unwrap();
+        if conn.is_none() {
+            let restarted = self.connect().map_err(|err| {
+                CallError::from(format!("could not restart the module host: {}", err))
+            })?;
+            self.restarts.fetch_add(1, Ordering::SeqCst);
+            *conn = Some(restarted);
+        }
+        let timeout = *self.timeout.lock().unwrap();
+        match conn.as_mut().unwrap().request(req, timeout) {
+            Ok(rtn) => Ok(rtn),
+            Err(err) if err.kind() == ErrorKind::TimedOut => {
+                // the late answer would be read as the answer of the next request
+                let mut late = conn.take().unwrap();
+                let _ = late.child.kill();
+                late.close();
+                Err(CallError::timeout(timeout.unwrap()))
+            }
+            Err(err) => Err(CallError::from(conn.take().unwrap().fail(err))),
+        }
+    }
+
+    /// Forwards a call, returning `None` if the DLL does not export the function
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the function
+    /// * `args` - the arguments
+    fn call(&self, name: &str, args: Vec<Owned>) -> Option<Owned> {
+        if let Some(key) = args.iter().find_map(|arg| find_object_key(arg)) {
+            let message = format!("`{}` cannot be sent to another process", key);
+            return Some(CallError::from(message).to_value());
+        }
+        let req = Value::new_arr(vec![
+            Value::new_str("call"),
+            Value::new_str(name),
+            Value::new_arr(args),
+        ]);
+        match self.request(&req) {
+            Ok(rtn) if is_unknown_function(&rtn) => None,
+            Ok(rtn) => Some(rtn),
+            Err(err) => Some(err.to_value()),
+        }
+    }
+}
+
+/// Checks whether `dy-host` answered that the DLL does not export a function
+fn is_unknown_function(val: &Value) -> bool {
+    val.as_map()
+        .is_some_and(|map| map.size() == 1 && map.at(UNKNOWN_KEY).is_some())
+}
+
+impl RemoteModule {
+    /// Loads a DLL in a new `dy-host` process
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the DLL
+    /// * `search_paths` - the list of directories where the DLL may be located in
+    pub fn new(name: &str, search_paths: &[&str]) -> Option<RemoteModule> {
+        RemoteModule::with_loader(name, &ModuleLoader::new().dirs(search_paths)).ok()
+    }
+
+    /// Loads the DLL at the exact given path in a new `dy-host` process
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the path of the DLL
+    pub fn open<P: AsRef<Path>>(path: P) -> Option<RemoteModule> {
+        RemoteModule::open_with_loader(path, &ModuleLoader::new()).ok()
+    }
+
+    /// Loads a DLL in a new `dy-host` process, searching it like `loader` would
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the DLL
+    /// * `loader` - the loader whose directories and policies are applied
+    pub fn with_loader(name: &str, loader: &ModuleLoader) -> Result<RemoteModule, LoadError> {
+        let target = Target::Load {
+            name: String::from(name),
+            candidates: loader.candidates(name),
+        };
+        RemoteModule::start(target, loader.allows_unversioned())
+    }
+
+    /// Loads the DLL at the exact given path in a new `dy-host` process,
+    /// applying the policies of `loader`
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the path of the DLL
+    /// * `loader` - the loader whose policies, e.g. `allow_unversioned`, are applied
+    pub fn open_with_loader<P: AsRef<Path>>(
+        path: P,
+        loader: &ModuleLoader,
+    ) -> Result<RemoteModule, LoadError> {
+        let target = Target::Open(path.as_ref().to_path_buf());
+        RemoteModule::start(target, loader.allows_unversioned())
+    }
+
+    fn start(target: Target, allow_unversioned: bool) -> Result<RemoteModule, LoadError> {
+        let inner = Inner {
+            host: host_path(),
+            target,
+            allow_unversioned,
+            conn: Mutex::new(None),
+            restarts: AtomicUsize::new(0),
+            timeout: Mutex::new(None),
+        };
+        let conn = inner.connect()?;
+        *inner.conn.lock().unwrap() = Some(conn);
+        Ok(RemoteModule {
+            inner: Arc::new(inner),
+        })
+    }
+
+    /// Returns the number of times `dy-host` has been restarted after a crash
+    pub fn restarts(&self) -> usize {
+        self.inner.restarts.load(Ordering::SeqCst)
+    }
+
+    /// Sets the time every clone waits for an answer of `dy-host`
+    ///
+    /// A request which times out fails with `CallError::Timeout`, and the
+    /// process is killed and restarted by the next request, so that a hung
+    /// DLL does not block its callers forever. There is no timeout by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - the time to wait, `None` to wait forever
+    pub fn set_timeout(&self, timeout: Option<Duration>) {
+        *self.inner.timeout.lock().unwrap() = timeout;
+    }
+
+    /// Returns the time every clone waits for an answer of `dy-host`
+    pub fn timeout(&self) -> Option<Duration> {
+        *self.inner.timeout.lock().unwrap()
+    }
+
+    /// Lists the functions the DLL declares in its manifest
+    ///
+    /// Returns an empty list if the DLL does not export a manifest or `dy-host` fails.
+    pub fn functions(&self) -> Vec<FunctionInfo> {
+        match self
+            .inner
+            .request(&Value::new_arr(vec![Value::new_str("functions")]))
+        {
+            Ok(rtn) => FunctionInfo::list_from_value(&rtn),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Retrieves an exported function from the DLL
+    ///
+    /// Returns `Ok(None)` if the DLL does not export the function, and an error
+    /// if `dy-host` could not be asked, e.g. because it crashed.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the function
+    pub fn get_fn(&self, name: &str) -> Result<Option<RemoteFunction>, CallError> {
+        let req = Value::new_arr(vec![Value::new_str("has_fn"), Value::new_str(name)]);
+        let rtn = self.inner.request(&req)?;
+        if bool::from_value(&rtn).map_err(CallError::unexpected_return)? {
+            Ok(Some(RemoteFunction {
+                module: self.clone(),
+                name: String::from(name),
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl ModuleApi for RemoteModule {
+    fn functions(&self) -> Vec<FunctionInfo> {
+        RemoteModule::functions(self)
+    }
+
+    fn has_fn(&self, name: &str) -> bool {
+        matches!(self.get_fn(name), Ok(Some(_)))
+    }
+
+    fn call_with_borrowed(&self, name: &str, args: &[Borrowed<'_>]) -> Option<Owned> {
+        self.inner
+            .call(name, args.iter().map(|arg| arg.copy()).collect())
+    }
+
+    fn call(&self, name: &str, args: Vec<Owned>) -> Option<Owned> {
+        self.inner.call(name, args)
+    }
+}
+
+impl RemoteFunction {
+    /// Returns the name of the function
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the DLL exporting the function
+    pub fn module(&self) -> &RemoteModule {
+        &self.module
+    }
+
+    /// Invokes the exported function
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments
+    pub fn call_with_borrowed(&self, args: &[Borrowed<'_>]) -> Owned {
+        self.call(args.iter().map(|arg| arg.copy()).collect())
+    }
+
+    /// Calls the exported function and disposes arguments after the invocation
+    ///
+    /// If `dy-host` crashes or times out, returns the error value of a `CallError`
+    /// saying so.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments
+    pub fn call(&self, args: Vec<Owned>) -> Owned {
+        self.module.inner.call(&self.name, args).unwrap_or_else(|| {
+            CallError::from(format!("no function named `{}`", self.name)).to_value()
+        })
+    }
+
+    /// Invokes the exported function, reporting a panic or an error of the function,
+    /// or a crash of `dy-host`, as `CallError`
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments
+    pub fn try_call_with_borrowed(&self, args: &[Borrowed<'_>]) -> Result<Owned, CallError> {
+        into_result(self.call_with_borrowed(args))
+    }
+
+    /// Calls the exported function and disposes arguments after the invocation,
+    /// reporting a panic or an error of the function, or a crash of `dy-host`, as `CallError`
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments
+    pub fn try_call(&self, args: Vec<Owned>) -> Result<Owned, CallError> {
+        into_result(self.call(args))
+    }
+
+    /// Calls the exported function with Rust types, converting the result back
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments, usually a tuple
+    pub fn call_typed<Args: IntoArgs, Ret: FromValue>(&self, args: Args) -> Result<Ret, CallError> {
+        let rtn = self.try_call(args.into_args())?;
+        Ret::from_value(&rtn).map_err(CallError::unexpected_return)
+    }
+
+    /// Calls the exported function from a worker thread, resolving to its result
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments
+    pub fn call_async(&self, args: Vec<Owned>) -> CallFuture {
+        let func = self.clone();
+        CallFuture::spawn(move || func.call(args))
+    }
+
+    /// Calls the exported function from a worker thread, resolving to its result or
+    /// the panic or the error of the function, or a crash of `dy-host`, as `CallError`
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments
+    pub fn try_call_async(
+        &self,
+        args: Vec<Owned>,
+    ) -> impl Future<Output = Result<Owned, CallError>> + Send {
+        let rtn = self.call_async(args);
+        async move { into_result(rtn.await) }
+    }
+}
+
+/// Loads the DLL named by the first request of a client
+///
+/// # Arguments
+///
+/// * `req` - the request
+fn open_module(req: &Value) -> Result<Module, LoadError> {
+    let invalid = || LoadError::Open {
+        path: PathBuf::new(),
+        message: String::from("invalid request"),
+    };
+    let op = req
+        .as_arr()
+        .and_then(|arr| arr.at(0))
+        .and_then(|op| String::from_value(&op).ok())
+        .ok_or_else(invalid)?;
+    match op.as_str() {
+        "open" => {
+            let (_, path, allow_unversioned) =
+                <(String, String, bool)>::from_value(req).map_err(|_| invalid())?;
+            ModuleLoader::new()
+                .allow_unversioned(allow_unversioned)
+                .open(path)
+        }
+        "load" => {
+            let (_, name, candidates, allow_unversioned) =
+                <(String, String, Vec<String>, bool)>::from_value(req).map_err(|_| invalid())?;
+            let candidates: Vec<PathBuf> = candidates.into_iter().map(PathBuf::from).collect();
+            ModuleLoader::new()
+                .allow_unversioned(allow_unversioned)
+                .load_candidates(&name, &candidates)
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Answers a request of a client
+///
+/// # Arguments
+///
+/// * `module` - the DLL
+/// * `req` - the request
+fn answer(module: &Module, req: &Value) -> Owned {
+    let invalid = || CallError::from("invalid request").to_value();
+    let arr = match req.as_arr() {
+        Some(arr) => arr,
+        None => return invalid(),
+    };
+    let get_str = |idx: usize| arr.at(idx).and_then(|s| String::from_value(&s).ok());
+    match (get_str(0).as_deref(), get_str(1)) {
+        (Some("functions"), None) => FunctionInfo::list_to_value(&module.functions()),
+        (Some("has_fn"), Some(name)) => Value::new_bool(module.get_fn(&name).is_some()),
+        (Some("call"), Some(name)) => {
+            let func = match module.get_fn(&name) {
+                Some(func) => func,
+                None => return Value::new_map(vec![(UNKNOWN_KEY, Value::new_str(&name))]),
+            };
+            match arr.at(2).as_ref().and_then(|args| args.as_arr()) {
+                Some(args) => func.call_with_borrowed(&args.iter().collect::<Vec<_>>()),
+                None => invalid(),
+            }
+        }
+        _ => invalid(),
+    }
+}
+
+/// Serves a client until it closes the connection
+///
+/// # Arguments
+///
+/// * `reader` - the requests of the client
+/// * `writer` - the answers to the client
+fn serve<R: Read, W: Write>(reader: R, writer: W) -> io::Result<()> {
+    let mut reader = BufReader::new(reader);
+    let mut writer = BufWriter::new(writer);
+
+    let req = read_value(&mut reader)?;
+    let module = open_module(&req);
+    let rtn = match &module {
+        Ok(_) => Value::new_null(),
+        Err(err) => load_error_to_value(err),
+    };
+    write_value(&mut writer, &rtn)?;
+    writer.flush()?;
+    let module = match module {
+        Ok(module) => module,
+        Err(_) => return Ok(()),
+    };
+
+    loop {
+        let req = match read_value(&mut reader) {
+            Ok(req) => req,
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        write_value(&mut writer, &answer(&module, &req))?;
+        writer.flush()?;
+    }
+}
+
+/// Runs `dy-host`, returning its exit code
+#[doc(hidden)]
+pub fn host_main() -> i32 {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let rtn = match args.as_slice() {
+        #[cfg(unix)]
+        [flag] if flag == "--stdin-socket" => {
+            use std::os::unix::io::FromRawFd;
+            use std::os::unix::net::UnixStream;
+
+            // the host passed its end of a socket pair as the standard input,
+            // which nothing else in this process reads
+            let stream = unsafe { UnixStream::from_raw_fd(0) };
+            stream.try_clone().and_then(|reader| serve(reader, stream))
+        }
+        [] => serve(io::stdin(), io::stdout()),
+        _ => {
+            eprintln!("usage: dy-host [--stdin-socket]");
+            return 2;
+        }
+    };
+    match rtn {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("dy-host: {}", err);
+            1
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use crate::api::ModuleApi;
+use crate::codec::{encode, read_value};
+use crate::error::CallError;
+use crate::manifest::FunctionInfo;
+use crate::value::*;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// The key of the function name in a recorded call
+pub(crate) const FUNCTION_KEY: &str = "function";
+/// The key of the arguments in a recorded call
+pub(crate) const ARGS_KEY: &str = "args";
+/// The key of the returned value in a recorded call
+pub(crate) const RETURN_KEY: &str = "return";
+
+/// Indicates a stand-in for a DLL serving calls recorded by a `Recorder`
+///
+/// A call is answered with the value recorded for the same function and the
+/// same arguments. Calls recorded several times are answered in the order they
+/// were recorded, the last answer being repeated once all have been served.
+/// A call without a recording returns an error value.
+#[derive(Debug)]
+pub struct ReplayModule {
+    names: Vec<String>,
+    calls: Mutex<BTreeMap<(String, Vec<u8>), Replies>>,
+}
+
+#[derive(Debug)]
+struct Replies {
+    rtns: Vec<Owned>,
+    next: usize,
+}
+
+impl ReplayModule {
+    /// Reads the calls recorded in a file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the path to the file
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<ReplayModule> {
+        ReplayModule::from_reader(File::open(path)?)
+    }
+
+    /// Reads recorded calls until the end of a reader
+    ///
+    /// # Arguments
+    ///
+    /// * `r` - the reader
+    pub fn from_reader<R: Read>(r: R) -> io::Result<ReplayModule> {
+        let mut r = BufReader::new(r);
+        let mut names = Vec::new();
+        let mut calls: BTreeMap<(String, Vec<u8>), Replies> = BTreeMap::new();
+        while !r.fill_buf()?.is_empty() {
+            let entry = read_value(&mut r)?;
+            let (name, args, rtn) = read_entry(&entry).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "malformed recorded call")
+            })?;
+            if !names.contains(&name) {
+                names.push(name.clone());
+            }
+            let replies = calls.entry((name, args)).or_insert(Replies {
+                rtns: Vec::new(),
+                next: 0,
+            });
+            replies.rtns.push(rtn);
+        }
+        Ok(ReplayModule {
+            names,
+            calls: Mutex::new(calls),
+        })
+    }
+
+    /// Returns the number of distinct calls recorded
+    pub fn len(&self) -> usize {
+        self.calls.lock().unwrap().len()
+    }
+
+    /// Returns `true` if no call is recorded
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+fn read_entry(entry: &Value) -> Option<(String, Vec<u8>, Owned)> {
+    let map = entry.as_map()?;
+    let name = map.at(FUNCTION_KEY)?.get_val().as_str()?.get();
+    let args = map.at(ARGS_KEY)?;
+    args.get_val().as_arr()?;
+    let rtn = map.at(RETURN_KEY)?.get_val().copy();
+    Some((name, encode(args.get_val()), rtn))
+}
+
+impl ModuleApi for ReplayModule {
+    fn functions(&self) -> Vec<FunctionInfo> {
+        self.names
+            .iter()
+            .map(|name| FunctionInfo::new(name))
+            .collect()
+    }
+
+    fn has_fn(&self, name: &str) -> bool {
+        self.names.iter().any(|known| known == name)
+    }
+
+    fn call_with_borrowed(&self, name: &str, args: &[Borrowed<'_>]) -> Option<Owned> {
+        if !self.has_fn(name) {
+            return None;
+        }
+        let args = Value::new_arr(args.iter().map(|arg| arg.copy()).collect());
+        let key = (String::from(name), encode(&args));
+        let mut calls = self.calls.lock().unwrap();
+        let rtn = match calls.get_mut(&key) {
+            Some(replies) => {
+                let rtn = replies.rtns[replies.next].copy();
+                replies.next = (replies.next + 1).min(replies.rtns.len() - 1);
+                rtn
+            }
+            None => CallError::from(format!(
+                "no recording of `{}` with the arguments {:?}",
+                name, args
+            ))
+            .to_value(),
+        };
+        Some(rtn)
+    }
+}
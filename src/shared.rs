@@ -0,0 +1,249 @@
+use crate::api::ModuleApi;
+use crate::callback::Callback;
+use crate::cancel::CancelToken;
+use crate::convert::{FromValue, IntoArgs};
+use crate::error::{into_result, CallError};
+use crate::exported::RawFunction;
+use crate::import::{Function, Module};
+use crate::manifest::FunctionInfo;
+use crate::pool::{self, CallFuture};
+use crate::value::*;
+use std::future::Future;
+use std::ops::Deref;
+use std::sync::mpsc::{sync_channel, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Indicates a DLL shared by reference counting
+///
+/// The DLL stays loaded as long as any clone of it or any `OwnedFunction`
+/// retrieved from it exists.
+#[derive(Clone)]
+pub struct SharedModule {
+    module: Arc<Module>,
+}
+
+/// Indicates an exported function keeping its DLL loaded
+///
+/// Unlike `Function`, it does not borrow the DLL, so it can be stored anywhere
+/// and sent to other threads. Calling it from several threads at once is only
+/// correct if the exported function itself is thread-safe.
+#[derive(Clone)]
+pub struct OwnedFunction {
+    module: SharedModule,
+    name: String,
+    raw: RawFunction,
+}
+
+impl SharedModule {
+    /// Creates a new `SharedModule` instance taking over a loaded DLL
+    ///
+    /// # Arguments
+    ///
+    /// * `module` - the DLL
+    pub fn new(module: Module) -> SharedModule {
+        SharedModule {
+            module: Arc::new(module),
+        }
+    }
+
+    /// Retrieves an exported function from the DLL
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the function
+    pub fn get_fn(&self, name: &str) -> Option<OwnedFunction> {
+        let raw = self.module.get_fn_by_ref(name)?.as_raw();
+        Some(OwnedFunction {
+            module: self.clone(),
+            name: String::from(name),
+            raw,
+        })
+    }
+}
+
+impl From<Module> for SharedModule {
+    fn from(module: Module) -> SharedModule {
+        SharedModule::new(module)
+    }
+}
+
+impl Deref for SharedModule {
+    type Target = Module;
+    fn deref(&self) -> &Module {
+        &self.module
+    }
+}
+
+impl ModuleApi for SharedModule {
+    fn functions(&self) -> Vec<FunctionInfo> {
+        self.module.functions()
+    }
+
+    fn has_fn(&self, name: &str) -> bool {
+        self.module.has_fn(name)
+    }
+
+    fn call_with_borrowed(&self, name: &str, args: &[Borrowed<'_>]) -> Option<Owned> {
+        ModuleApi::call_with_borrowed(&*self.module, name, args)
+    }
+
+    fn call(&self, name: &str, args: Vec<Owned>) -> Option<Owned> {
+        ModuleApi::call(&*self.module, name, args)
+    }
+}
+
+impl OwnedFunction {
+    /// Returns the name of the function
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the DLL exporting the function
+    pub fn module(&self) -> &SharedModule {
+        &self.module
+    }
+
+    /// Borrows the function as a `Function`
+    pub fn as_function(&self) -> Function<'_> {
+        // the DLL is kept loaded by `self.module`
+        unsafe { Function::from_raw_in(&self.module, &self.name, self.raw) }
+    }
+
+    /// Invokes the exported function
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments
+    pub fn call_with_borrowed(&self, args: &[Borrowed<'_>]) -> Owned {
+        self.as_function().call_with_borrowed(args)
+    }
+
+    /// Calls the exported function and disposes arguments after the invocation
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments
+    pub fn call(&self, args: Vec<Owned>) -> Owned {
+        self.as_function().call(args)
+    }
+
+    /// Invokes the exported function, reporting a panic or an error of the function as `CallError`
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments
+    pub fn try_call_with_borrowed(&self, args: &[Borrowed<'_>]) -> Result<Owned, CallError> {
+        self.as_function().try_call_with_borrowed(args)
+    }
+
+    /// Calls the exported function and disposes arguments after the invocation,
+    /// reporting a panic or an error of the function as `CallError`
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments
+    pub fn try_call(&self, args: Vec<Owned>) -> Result<Owned, CallError> {
+        self.as_function().try_call(args)
+    }
+
+    /// Calls the exported function with Rust types, converting the result back
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments, usually a tuple
+    pub fn call_typed<Args: IntoArgs, Ret: FromValue>(&self, args: Args) -> Result<Ret, CallError> {
+        self.as_function().call_typed(args)
+    }
+
+    /// Calls the exported function on a worker thread, resolving to its result
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments
+    pub fn call_async(&self, args: Vec<Owned>) -> CallFuture {
+        let func = self.clone();
+        CallFuture::spawn(move || func.call(args))
+    }
+
+    /// Calls the exported function on a worker thread, resolving to its result
+    /// or the panic or the error of the function as `CallError`
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments
+    pub fn try_call_async(
+        &self,
+        args: Vec<Owned>,
+    ) -> impl Future<Output = Result<Owned, CallError>> + Send {
+        let rtn = self.call_async(args);
+        async move { into_result(rtn.await) }
+    }
+
+    /// Calls the exported function on a worker thread, giving up after `timeout`
+    ///
+    /// A call which times out keeps running on its worker, and keeps the DLL
+    /// and the callbacks and cancellation tokens passed as arguments alive,
+    /// until it returns.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments
+    /// * `timeout` - the time to wait for the result
+    pub fn call_with_timeout(
+        &self,
+        args: Vec<Owned>,
+        timeout: Duration,
+    ) -> Result<Owned, CallError> {
+        self.call_on_worker(args, timeout)
+    }
+
+    /// Calls an exported function taking a `CancelToken` as its last parameter on a worker
+    /// thread, cancelling the token if the call does not return within `timeout`
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - the arguments, without the token
+    /// * `token` - the token passed to the function, which may also be cancelled by the caller
+    /// * `timeout` - the time to wait for the result
+    pub fn call_cancellable(
+        &self,
+        mut args: Vec<Owned>,
+        token: &CancelToken,
+        timeout: Duration,
+    ) -> Result<Owned, CallError> {
+        args.push(token.to_value());
+        let rtn = self.call_on_worker(args, timeout);
+        if let Err(CallError::Timeout { .. }) = rtn {
+            token.cancel();
+        }
+        rtn
+    }
+
+    fn call_on_worker(&self, args: Vec<Owned>, timeout: Duration) -> Result<Owned, CallError> {
+        // the objects must outlive the call, even if the caller gave up and dropped them
+        let callbacks: Vec<Callback> = args
+            .iter()
+            .filter_map(|arg| Callback::from_value(arg).ok())
+            .collect();
+        let tokens: Vec<CancelToken> = args
+            .iter()
+            .filter_map(|arg| CancelToken::from_value(arg).ok())
+            .collect();
+        let (sender, receiver) = sync_channel(1);
+        let func = self.clone();
+        pool::spawn(move || {
+            let rtn = func.call(args);
+            drop((callbacks, tokens));
+            let _ = sender.send(rtn);
+        });
+        match receiver.recv_timeout(timeout) {
+            Ok(rtn) => into_result(rtn),
+            Err(RecvTimeoutError::Timeout) => Err(CallError::timeout(timeout)),
+            Err(RecvTimeoutError::Disconnected) => Err(CallError::Panic {
+                message: String::from("the worker thread panicked"),
+                location: None,
+            }),
+        }
+    }
+}
@@ -0,0 +1,572 @@
+//! A text syntax of `dy` values, extending JSON
+//!
+//! Every JSON document is a valid literal; numbers without a fraction or an
+//! exponent are integers, other numbers and integers out of the range of
+//! `i64` are floating point numbers. The syntax also has
+//!
+//! * `nan`, `inf` and `-inf` floating point numbers,
+//! * typed arrays `b[true, false]`, `i[1, 2]` and `f[1.5, 2]`,
+//! * byte arrays `x"00ff"` in hexadecimal,
+//! * map keys without quotes, e.g. `{name: "dy"}`,
+//! * trailing commas in arrays and maps.
+//!
+//! `parse_with` also reads variables, e.g. `$1` or `$config`, whose values
+//! are given by the caller.
+//!
+//! `to_string` prints any value so that `parse` reads it back unchanged, unless
+//! it holds strings which are not valid UTF-8, whose invalid bytes are printed
+//! as U+FFFD.
+
+use crate::value::*;
+use std::error::Error;
+use std::fmt::{self, Write};
+
+/// The deepest nesting of arrays and maps accepted by `parse`
+pub const MAX_DEPTH: usize = 512;
+
+/// Indicates a literal could not be parsed
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// the byte offset of the error in the literal
+    pub offset: usize,
+    /// what was wrong
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.offset)
+    }
+}
+
+impl Error for ParseError {}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+    vars: &'a dyn Fn(&str) -> Option<Owned>,
+}
+
+impl<'a> Parser<'a> {
+    fn error<T>(&self, message: &str) -> Result<T, ParseError> {
+        Err(ParseError {
+            offset: self.pos,
+            message: String::from(message),
+        })
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn skip_ws(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_ws();
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ParseError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            self.error(&format!("expected `{}`", c))
+        }
+    }
+
+    fn ident(&mut self) -> &'a str {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    /// Parses comma-separated items up to `close`, allowing a trailing comma
+    fn items<F>(&mut self, close: char, mut item: F) -> Result<(), ParseError>
+    where
+        F: FnMut(&mut Self) -> Result<(), ParseError>,
+    {
+        loop {
+            if self.eat(close) {
+                return Ok(());
+            }
+            item(self)?;
+            if !self.eat(',') {
+                return self.expect(close);
+            }
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Owned, ParseError> {
+        if depth > MAX_DEPTH {
+            return self.error("nested too deeply");
+        }
+        self.skip_ws();
+        match self.peek() {
+            Some('"') => Ok(Value::new_str(&self.string()?)),
+            Some('[') => {
+                self.pos += 1;
+                let mut elems = Vec::new();
+                self.items(']', |p| {
+                    elems.push(p.value(depth + 1)?);
+                    Ok(())
+                })?;
+                Ok(Value::new_arr(elems))
+            }
+            Some('{') => {
+                self.pos += 1;
+                let mut keys = Vec::new();
+                let mut vals = Vec::new();
+                self.items('}', |p| {
+                    p.skip_ws();
+                    let key = match p.peek() {
+                        Some('"') => p.string()?,
+                        _ => String::from(p.ident()),
+                    };
+                    if key.is_empty() {
+                        return p.error("expected a key");
+                    }
+                    p.expect(':')?;
+                    vals.push(p.value(depth + 1)?);
+                    keys.push(key);
+                    Ok(())
+                })?;
+                Ok(Value::new_map(
+                    keys.iter().map(String::as_str).zip(vals).collect(),
+                ))
+            }
+            Some('$') => self.var(),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(c) if c.is_ascii_alphabetic() => self.word(),
+            Some(_) => self.error("expected a value"),
+            None => self.error("unexpected end of input"),
+        }
+    }
+
+    fn var(&mut self) -> Result<Owned, ParseError> {
+        let start = self.pos;
+        self.pos += 1;
+        let name = self.ident();
+        if name.is_empty() {
+            return self.error("expected a variable name");
+        }
+        match (self.vars)(name) {
+            Some(val) => Ok(val),
+            None => {
+                self.pos = start;
+                self.error(&format!("unknown variable `${}`", name))
+            }
+        }
+    }
+
+    fn word(&mut self) -> Result<Owned, ParseError> {
+        let start = self.pos;
+        let word = self.ident();
+        match word {
+            "null" => Ok(Value::new_null()),
+            "true" => Ok(Value::new_bool(true)),
+            "false" => Ok(Value::new_bool(false)),
+            "nan" => Ok(Value::new_float(f64::NAN)),
+            "inf" => Ok(Value::new_float(f64::INFINITY)),
+            "b" | "i" | "f" if self.peek() == Some('[') => {
+                self.pos += 1;
+                match word {
+                    "b" => {
+                        let mut elems = Vec::new();
+                        self.items(']', |p| {
+                            p.skip_ws();
+                            match p.ident() {
+                                "true" => elems.push(true),
+                                "false" => elems.push(false),
+                                _ => return p.error("expected a boolean"),
+                            }
+                            Ok(())
+                        })?;
+                        Ok(Value::new_bool_arr(&elems))
+                    }
+                    "i" => {
+                        let mut elems = Vec::new();
+                        self.items(']', |p| {
+                            p.skip_ws();
+                            match p.number()?.as_int() {
+                                Some(i) => elems.push(i.get()),
+                                None => return p.error("expected an integer"),
+                            }
+                            Ok(())
+                        })?;
+                        Ok(Value::new_int_arr(&elems))
+                    }
+                    _ => {
+                        let mut elems = Vec::new();
+                        self.items(']', |p| {
+                            p.skip_ws();
+                            let num = if matches!(p.peek(), Some(c) if c.is_ascii_alphabetic()) {
+                                p.word()?
+                            } else {
+                                p.number()?
+                            };
+                            match num.as_type() {
+                                As::Int(i) => elems.push(i.get() as f64),
+                                As::Float(f) => elems.push(f.get()),
+                                _ => return p.error("expected a number"),
+                            }
+                            Ok(())
+                        })?;
+                        Ok(Value::new_float_arr(&elems))
+                    }
+                }
+            }
+            "x" if self.peek() == Some('"') => {
+                let hex = self.string()?;
+                if hex.len() % 2 != 0 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                    return self.error("expected pairs of hexadecimal digits");
+                }
+                let bytes: Vec<u8> = (0..hex.len())
+                    .step_by(2)
+                    .map(|idx| u8::from_str_radix(&hex[idx..idx + 2], 16).unwrap())
+                    .collect();
+                Ok(Value::new_bytes(&bytes))
+            }
+            _ => {
+                self.pos = start;
+                self.error("expected a value")
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Owned, ParseError> {
+        let start = self.pos;
+        if self.rest().starts_with("-inf") {
+            self.pos += 4;
+            return Ok(Value::new_float(f64::NEG_INFINITY));
+        }
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c)))
+            .unwrap_or(rest.len());
+        let text = &rest[..len];
+        self.pos += len;
+        // a fraction or an exponent fails the first parse
+        let parsed = match text.parse::<i64>() {
+            Ok(int) => Some(Value::new_int(int)),
+            Err(_) => text.parse::<f64>().ok().map(Value::new_float),
+        };
+        match parsed {
+            Some(val) => Ok(val),
+            None => {
+                self.pos = start;
+                self.error("expected a number")
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        self.expect('"')?;
+        let mut rtn = String::new();
+        loop {
+            let c = match self.peek() {
+                Some(c) => c,
+                None => return self.error("unterminated string"),
+            };
+            self.pos += c.len_utf8();
+            match c {
+                '"' => return Ok(rtn),
+                '\\' => {
+                    let escape = match self.peek() {
+                        Some(escape) => escape,
+                        None => return self.error("unterminated string"),
+                    };
+                    self.pos += 1;
+                    match escape {
+                        '"' | '\\' | '/' => rtn.push(escape),
+                        'b' => rtn.push('\u{8}'),
+                        'f' => rtn.push('\u{c}'),
+                        'n' => rtn.push('\n'),
+                        'r' => rtn.push('\r'),
+                        't' => rtn.push('\t'),
+                        'u' => rtn.push(self.unicode_escape()?),
+                        _ => return self.error("invalid escape"),
+                    }
+                }
+                '\0' => return self.error("strings cannot contain NUL"),
+                _ => rtn.push(c),
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, ParseError> {
+        let digits = self.rest().get(..4).unwrap_or("");
+        let is_hex = digits.len() == 4 && digits.chars().all(|c| c.is_ascii_hexdigit());
+        match u32::from_str_radix(digits, 16) {
+            Ok(code) if is_hex => {
+                self.pos += 4;
+                Ok(code)
+            }
+            _ => self.error("expected 4 hexadecimal digits"),
+        }
+    }
+
+    fn unicode_escape(&mut self) -> Result<char, ParseError> {
+        let high = self.hex4()?;
+        let code = if (0xd800..0xdc00).contains(&high) {
+            if !self.rest().starts_with("\\u") {
+                return self.error("expected a low surrogate");
+            }
+            self.pos += 2;
+            let low = self.hex4()?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return self.error("expected a low surrogate");
+            }
+            0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+        } else {
+            high
+        };
+        match char::from_u32(code) {
+            Some('\0') => self.error("strings cannot contain NUL"),
+            Some(c) => Ok(c),
+            None => self.error("invalid unicode escape"),
+        }
+    }
+}
+
+/// Parses a literal
+///
+/// # Arguments
+///
+/// * `src` - the literal
+pub fn parse(src: &str) -> Result<Owned, ParseError> {
+    parse_with(src, |_| None)
+}
+
+/// Parses a literal which may contain variables
+///
+/// # Arguments
+///
+/// * `src` - the literal
+/// * `vars` - returns the value of a variable by its name without `$`, or
+///   `None` if there is no such variable
+pub fn parse_with<F>(src: &str, vars: F) -> Result<Owned, ParseError>
+where
+    F: Fn(&str) -> Option<Owned>,
+{
+    let mut parser = Parser {
+        src,
+        pos: 0,
+        vars: &vars,
+    };
+    let rtn = parser.value(0)?;
+    parser.skip_ws();
+    if parser.pos != src.len() {
+        return parser.error("unexpected characters after the value");
+    }
+    Ok(rtn)
+}
+
+fn write_str(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn write_float(out: &mut String, f: f64) {
+    if f.is_nan() {
+        out.push_str("nan");
+    } else {
+        // `Debug` keeps a fraction or an exponent, and prints infinities as `inf`
+        write!(out, "{:?}", f).unwrap();
+    }
+}
+
+fn write_list<T, F>(out: &mut String, open: &str, items: &[T], mut write_item: F)
+where
+    F: FnMut(&mut String, &T),
+{
+    out.push_str(open);
+    for (idx, item) in items.iter().enumerate() {
+        if idx > 0 {
+            out.push_str(", ");
+        }
+        write_item(out, item);
+    }
+    out.push(']');
+}
+
+fn write_value(out: &mut String, val: &Value, indent: Option<usize>) {
+    let newline = |out: &mut String, level: usize| {
+        out.push('\n');
+        out.push_str(&"  ".repeat(level));
+    };
+    match val.as_type() {
+        As::Null(_) => out.push_str("null"),
+        As::Bool(b) => write!(out, "{}", b.get()).unwrap(),
+        As::Int(i) => write!(out, "{}", i.get()).unwrap(),
+        As::Float(f) => write_float(out, f.get()),
+        As::Str(s) => write_str(out, &s.get()),
+        As::BoolArr(arr) => {
+            let data: Vec<bool> = (0..arr.len()).filter_map(|idx| arr.at(idx)).collect();
+            write_list(out, "b[", &data, |out, b| write!(out, "{}", b).unwrap());
+        }
+        As::Bytes(bytes) => {
+            out.push_str("x\"");
+            for b in bytes.data() {
+                write!(out, "{:02x}", b).unwrap();
+            }
+            out.push('"');
+        }
+        As::IntArr(arr) => write_list(out, "i[", arr.data(), |out, i| {
+            write!(out, "{}", i).unwrap()
+        }),
+        As::FloatArr(arr) => write_list(out, "f[", arr.data(), |out, f| write_float(out, *f)),
+        As::Arr(arr) if arr.len() == 0 => out.push_str("[]"),
+        As::Arr(arr) => {
+            out.push('[');
+            for (idx, elem) in arr.iter().enumerate() {
+                if idx > 0 {
+                    out.push(',');
+                    if indent.is_none() {
+                        out.push(' ');
+                    }
+                }
+                if let Some(level) = indent {
+                    newline(out, level + 1);
+                }
+                write_value(out, &elem, indent.map(|level| level + 1));
+            }
+            if let Some(level) = indent {
+                newline(out, level);
+            }
+            out.push(']');
+        }
+        As::Map(map) if map.size() == 0 => out.push_str("{}"),
+        As::Map(map) => {
+            out.push('{');
+            for (idx, pair) in map.iter().enumerate() {
+                if idx > 0 {
+                    out.push(',');
+                    if indent.is_none() {
+                        out.push(' ');
+                    }
+                }
+                if let Some(level) = indent {
+                    newline(out, level + 1);
+                }
+                write_str(out, pair.get_key());
+                out.push_str(": ");
+                write_value(out, pair.get_val(), indent.map(|level| level + 1));
+            }
+            if let Some(level) = indent {
+                newline(out, level);
+            }
+            out.push('}');
+        }
+    }
+}
+
+/// Prints a value on a single line
+///
+/// Invalid UTF-8 in strings is replaced by U+FFFD, like `AsStrValue::get`.
+///
+/// # Arguments
+///
+/// * `val` - the value to print
+pub fn to_string(val: &Value) -> String {
+    let mut rtn = String::new();
+    write_value(&mut rtn, val, None);
+    rtn
+}
+
+/// Prints a value with one element of a generic array or map per line
+///
+/// # Arguments
+///
+/// * `val` - the value to print
+pub fn to_string_pretty(val: &Value) -> String {
+    let mut rtn = String::new();
+    write_value(&mut rtn, val, Some(0));
+    rtn
+}
+
+/// Prints a value as JSON, which loses the difference between arrays and
+/// typed arrays
+///
+/// Byte arrays become arrays of integers, and floating point numbers which
+/// are not finite become `null`.
+///
+/// # Arguments
+///
+/// * `val` - the value to print
+pub fn to_json(val: &Value) -> String {
+    let mut rtn = String::new();
+    write_json(&mut rtn, val);
+    rtn
+}
+
+fn write_json_float(out: &mut String, f: f64) {
+    if f.is_finite() {
+        write!(out, "{:?}", f).unwrap();
+    } else {
+        out.push_str("null");
+    }
+}
+
+fn write_json(out: &mut String, val: &Value) {
+    match val.as_type() {
+        As::BoolArr(arr) => {
+            let data: Vec<bool> = (0..arr.len()).filter_map(|idx| arr.at(idx)).collect();
+            write_list(out, "[", &data, |out, b| write!(out, "{}", b).unwrap());
+        }
+        As::Bytes(bytes) => write_list(out, "[", bytes.data(), |out, b| {
+            write!(out, "{}", b).unwrap()
+        }),
+        As::IntArr(arr) => write_list(out, "[", arr.data(), |out, i| write!(out, "{}", i).unwrap()),
+        As::FloatArr(arr) => write_list(out, "[", arr.data(), |out, f| write_json_float(out, *f)),
+        As::Float(f) => write_json_float(out, f.get()),
+        As::Arr(arr) => {
+            out.push('[');
+            for (idx, elem) in arr.iter().enumerate() {
+                if idx > 0 {
+                    out.push_str(", ");
+                }
+                write_json(out, &elem);
+            }
+            out.push(']');
+        }
+        As::Map(map) => {
+            out.push('{');
+            for (idx, pair) in map.iter().enumerate() {
+                if idx > 0 {
+                    out.push_str(", ");
+                }
+                write_str(out, pair.get_key());
+                out.push_str(": ");
+                write_json(out, pair.get_val());
+            }
+            out.push('}');
+        }
+        _ => write_value(out, val, None),
+    }
+}
@@ -9,6 +9,7 @@ use std::mem::ManuallyDrop;
 use std::ops::Deref;
 use std::ptr::null;
 use std::slice::from_raw_parts;
+use std::sync::Arc;
 
 /// A pointer to a `dy` value.
 pub type ValuePtr = dy_t;
@@ -95,6 +96,16 @@ impl Value {
         unsafe { Owned::from_ptr(dy_make_str(s.as_ptr())) }
     }
 
+    /// Makes a new string from raw bytes, which need not be UTF-8
+    ///
+    /// # Arguments
+    ///
+    /// * `v` - the bytes of the string, without any NUL byte
+    pub(crate) fn new_str_bytes(v: &[u8]) -> Owned {
+        let s = CString::new(v).unwrap();
+        unsafe { Owned::from_ptr(dy_make_str(s.as_ptr())) }
+    }
+
     /// Makes a new generic array
     ///
     /// # Arguments
@@ -153,6 +164,17 @@ impl Borrowed<'_> {
     }
 }
 
+impl<'a> Borrowed<'a> {
+    /// Returns the bytes of a string value as stored, borrowed as long as the
+    /// value, or `None` if the value is not a string
+    pub fn str_bytes(&self) -> Option<&'a [u8]> {
+        if self.get_type() != Type::Str {
+            return None;
+        }
+        Some(unsafe { CStr::from_ptr(dy_get_str_data(self.val.ptr)) }.to_bytes())
+    }
+}
+
 impl<'a> Deref for Borrowed<'a> {
     type Target = Value;
     fn deref(&self) -> &Value {
@@ -192,6 +214,81 @@ impl Drop for Owned {
     }
 }
 
+// A value is a tree allocated with `malloc` which refers to no thread-local or
+// global state; `dy_dispose` frees it with `free`, so it may be used and
+// disposed on any thread.
+unsafe impl Send for Owned {}
+
+/// The type indicating an immutable `dy` value shared by reference counting.
+///
+/// Clones are cheap and may be read from many threads at once: `dy` never
+/// modifies a value once it is made.
+#[derive(Debug, Clone)]
+pub struct SharedValue {
+    val: Arc<SyncOwned>,
+}
+
+#[derive(Debug)]
+struct SyncOwned(Owned);
+
+// `SharedValue` only hands out `&Value`, whose methods reach the C library
+// through these functions alone:
+// - `dy_get_type`, `dy_get_b`, `dy_get_i`, `dy_get_f`, `dy_get_str_len`,
+//   `dy_get_str_data`, the `dy_get_*arr_len`, `dy_get_*arr_idx`,
+//   `dy_get_*_data`, `dy_get_arr_len`, `dy_get_arr_idx`, `dy_get_map_len` and
+//   `dy_get_map_key` only read the tree and return pointers into it;
+// - `dy_copy` reads the tree and allocates a new one;
+// - `dy_make_map_iter` allocates a cursor owned by the `MapIter`, which
+//   `dy_get_map_iter` advances and `dy_dispose_map_iter` frees, so every
+//   iteration has its own cursor and the map itself is only read.
+// None of them writes to the value, so reading it from many threads is a
+// race-free series of reads.
+unsafe impl Sync for SyncOwned {}
+
+impl SharedValue {
+    /// Creates a new shared value taking over an owned value
+    ///
+    /// # Arguments
+    ///
+    /// * `val` - the value to share
+    pub fn new(val: Owned) -> SharedValue {
+        SharedValue {
+            val: Arc::new(SyncOwned(val)),
+        }
+    }
+
+    /// Returns the value, copying it if other clones are alive
+    pub fn into_owned(self) -> Owned {
+        match Arc::try_unwrap(self.val) {
+            Ok(val) => val.0,
+            Err(val) => val.0.copy(),
+        }
+    }
+
+    /// Returns `true` if both instances share the same value
+    ///
+    /// # Arguments
+    ///
+    /// * `a` - the first instance
+    /// * `b` - the second instance
+    pub fn ptr_eq(a: &SharedValue, b: &SharedValue) -> bool {
+        Arc::ptr_eq(&a.val, &b.val)
+    }
+}
+
+impl Deref for SharedValue {
+    type Target = Value;
+    fn deref(&self) -> &Value {
+        &self.val.0
+    }
+}
+
+impl From<Owned> for SharedValue {
+    fn from(val: Owned) -> SharedValue {
+        SharedValue::new(val)
+    }
+}
+
 macro_rules! def_type {
     (
         $(
@@ -354,7 +451,12 @@ impl<'a> AsStrValue<'a> {
         unsafe { dy_get_str_len(self.val.ptr) as usize }
     }
 
-    /// Makes a string instance from this value
+    /// Returns the bytes of the string as stored, which may not be valid UTF-8
+    pub fn as_bytes(&self) -> &'a [u8] {
+        unsafe { CStr::from_ptr(dy_get_str_data(self.val.ptr)) }.to_bytes()
+    }
+
+    /// Makes a string instance from this value, replacing invalid UTF-8
     pub fn get(&self) -> String {
         match unsafe { CStr::from_ptr(dy_get_str_data(self.val.ptr)) }.to_string_lossy() {
             Cow::Borrowed(s) => String::from(s),
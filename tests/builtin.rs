@@ -0,0 +1,62 @@
+#![cfg(all(feature = "import", feature = "builtin"))]
+
+use dy::*;
+use std::process::Command;
+
+dy::exported! {
+    pub fn builtin_add(a: i64, b: i64) -> i64 {
+        a + b
+    }
+
+    pub fn builtin_fail() -> Result<i64, CallError> {
+        Err(CallError::new("builtin failure"))
+    }
+}
+
+// an exported function written by hand, which is registered explicitly
+unsafe extern "C" fn builtin_len(args: *const ValuePtr, len: usize) -> ValuePtr {
+    invoke_exported(args, len, |args| args.len() as i64)
+}
+
+dy::register!(builtin_len);
+
+#[test]
+fn static_module_test() {
+    assert!(StaticModule::new("no_such_crate").is_none());
+
+    let metrics = std::sync::Arc::new(MetricsHook::new());
+    let mut m = StaticModule::new("builtin").unwrap();
+    m.add_hook(metrics.clone());
+    let mut names: Vec<String> = m.functions().into_iter().map(|info| info.name).collect();
+    names.sort();
+    assert_eq!(names, vec!["builtin_add", "builtin_fail", "builtin_len"]);
+
+    let f = m.get_fn("builtin_add").unwrap();
+    assert_eq!(f.name(), "builtin_add");
+    assert_eq!(f.call_typed((2i64, 3i64)), Ok(5i64));
+    let err = m
+        .get_fn("builtin_fail")
+        .unwrap()
+        .try_call(vec![])
+        .unwrap_err();
+    assert_eq!(err.message(), "builtin failure");
+    let res = m.call_typed::<_, i64>("builtin_len", (1.0, "a", true));
+    assert_eq!(res, Ok(3));
+    assert!(m.get_fn("missing").is_none());
+    assert_eq!(metrics.get("builtin_add").unwrap().calls, 1);
+
+    assert!(StaticModule::all().unwrap().has_fn("builtin_len"));
+    assert!(StaticFunction::all().any(|func| func.crate_name() == "builtin"));
+}
+
+#[test]
+fn duplicate_names_test() {
+    // `builtin_test` links two crates exporting `checked_sqrt` into one binary
+    let crate_path = format!("{}/tests/builtin_test", env!("CARGO_MANIFEST_DIR"));
+    let status = Command::new("cargo")
+        .arg("run")
+        .current_dir(&crate_path)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
@@ -0,0 +1,22 @@
+use dy::*;
+
+/// Returns the negative square root of a number, exported under the same
+/// name as the one of `dll_test`
+#[export]
+pub fn checked_sqrt(x: f64) -> f64 {
+    -x.sqrt()
+}
+
+fn main() {
+    // links `dll_test`, whose functions are registered statically as well
+    assert_eq!(dll_test::repeat(String::from("ab"), 2), "abab");
+
+    match StaticModule::all() {
+        Err(LoadError::DuplicateFunctions(names)) => assert_eq!(names, vec!["checked_sqrt"]),
+        _ => panic!("Expected duplicate functions"),
+    }
+    let ours = StaticModule::new("builtin_test").unwrap();
+    assert_eq!(ours.call_typed::<_, f64>("checked_sqrt", (4.0,)), Ok(-2.0));
+    let theirs = StaticModule::new("dll_test").unwrap();
+    assert_eq!(theirs.call_typed::<_, f64>("checked_sqrt", (4.0,)), Ok(2.0));
+}
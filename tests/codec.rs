@@ -0,0 +1,82 @@
+use dy::codec::*;
+use dy::*;
+use std::io::ErrorKind;
+
+#[test]
+fn codec_round_trip_test() {
+    let val = Value::new_map(vec![
+        ("null", Value::new_null()),
+        ("bool", Value::new_bool(true)),
+        ("int", Value::new_int(-42)),
+        ("nan", Value::new_float(f64::NAN)),
+        ("str", Value::new_str("Hello, 世界")),
+        ("bool_arr", Value::new_bool_arr(&[true, false])),
+        ("bytes", Value::new_bytes(&[0, 1, 255])),
+        ("int_arr", Value::new_int_arr(&[i64::MIN, i64::MAX])),
+        ("float_arr", Value::new_float_arr(&[-0.0, 1.5])),
+        (
+            "arr",
+            Value::new_arr(vec![Value::new_int(1), Value::new_arr(Vec::new())]),
+        ),
+    ]);
+    let buf = encode(&val);
+    let decoded = decode(&buf).unwrap();
+    assert_eq!(encode(&decoded), buf);
+
+    let map = decoded.as_map().unwrap();
+    let get = |key: &str| map.at(key).unwrap().get_val().copy();
+    assert!(get("null").is_null());
+    assert_eq!(get("int").as_int().unwrap().get(), -42);
+    assert!(get("nan").as_float().unwrap().get().is_nan());
+    assert_eq!(get("str").as_str().unwrap().get(), "Hello, 世界");
+    assert_eq!(get("bytes").as_bytes().unwrap().data(), &[0, 1, 255]);
+    let float_arr = get("float_arr");
+    let float_arr = float_arr.as_float_arr().unwrap();
+    assert!(float_arr.data()[0].is_sign_negative());
+
+    let mut stream = Vec::new();
+    write_value(&mut stream, &Value::new_int(1)).unwrap();
+    write_value(&mut stream, &Value::new_str("two")).unwrap();
+    let mut reader = stream.as_slice();
+    assert_eq!(read_value(&mut reader).unwrap().as_int().unwrap().get(), 1);
+    assert_eq!(
+        read_value(&mut reader).unwrap().as_str().unwrap().get(),
+        "two"
+    );
+    assert!(reader.is_empty());
+
+    // strings made by C code need not be UTF-8 and are kept as they are
+    let buf = [4, 2, 0, 0, 0, 0, 0, 0, 0, 0xff, b'a'];
+    let decoded = decode(&buf).unwrap();
+    assert_eq!(decoded.as_str().unwrap().as_bytes(), &[0xff, b'a']);
+    assert_eq!(encode(&decoded), buf);
+}
+
+struct Marker;
+
+unsafe impl HandleType for Marker {
+    const TAG: &'static str = "codec::Marker";
+}
+
+#[test]
+fn codec_error_test() {
+    let buf = encode(&Value::new_str("truncated"));
+    let err = decode(&buf[..buf.len() - 1]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+
+    assert_eq!(decode(&[11]).unwrap_err().kind(), ErrorKind::InvalidData);
+    assert_eq!(decode(&[1, 2]).unwrap_err().kind(), ErrorKind::InvalidData);
+    assert_eq!(decode(&[0, 0]).unwrap_err().kind(), ErrorKind::InvalidData);
+
+    let token = CancelToken::new();
+    let callback = Callback::new(|_: &[Borrowed<'_>]| ());
+    let handle = Handle::new(Marker);
+    for val in [token.to_value(), callback.to_value(), handle.to_value()] {
+        let buf = encode(&Value::new_arr(vec![val]));
+        assert_eq!(decode(&buf).unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    let mut nested = [9, 1, 0, 0, 0, 0, 0, 0, 0].repeat(MAX_DEPTH + 1);
+    nested.push(0);
+    assert_eq!(decode(&nested).unwrap_err().kind(), ErrorKind::InvalidData);
+}
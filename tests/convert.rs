@@ -0,0 +1,65 @@
+use dy::*;
+use std::collections::HashMap;
+
+#[test]
+fn primitive_convert_test() {
+    assert_eq!(i64::from_value(&15i64.into_value()), Ok(15));
+    assert_eq!(f64::from_value(&2.5.into_value()), Ok(2.5));
+    assert_eq!(
+        String::from_value(&"hello".into_value()),
+        Ok(String::from("hello"))
+    );
+    assert_eq!(Option::<bool>::from_value(&Value::new_null()), Ok(None));
+    assert_eq!(
+        u8::from_value(&Value::new_int(300)),
+        Err(ConvertError::Invalid(String::from(
+            "300 is out of the range of u8"
+        )))
+    );
+    assert_eq!(
+        i64::from_value(&Value::new_str("15")),
+        Err(ConvertError::Type {
+            expected: Type::Int,
+            found: Type::Str
+        })
+    );
+}
+
+#[test]
+fn array_convert_test() {
+    let val = vec![2i64, 3, 4].into_value();
+    assert!(val.is_int_arr());
+    assert_eq!(Vec::<i64>::from_value(&val), Ok(vec![2, 3, 4]));
+
+    let val = vec![vec![1u8, 2], vec![3]].into_value();
+    assert!(val.is_arr());
+    assert_eq!(
+        Vec::<Vec<u8>>::from_value(&val),
+        Ok(vec![vec![1, 2], vec![3]])
+    );
+
+    let val = Value::new_arr(vec![Value::new_int(1), Value::new_int(2)]);
+    assert_eq!(Vec::<i64>::from_value(&val), Ok(vec![1, 2]));
+
+    let val = (5i64, String::from("x")).into_value();
+    assert_eq!(
+        <(i64, String)>::from_value(&val),
+        Ok((5, String::from("x")))
+    );
+    assert_eq!(
+        <(i64, String, bool)>::from_value(&val),
+        Err(ConvertError::Length {
+            expected: 3,
+            found: 2
+        })
+    );
+}
+
+#[test]
+fn map_convert_test() {
+    let mut map = HashMap::new();
+    map.insert(String::from("foo"), vec![1.5, 2.5]);
+    map.insert(String::from("bar"), vec![]);
+    let val = map.clone().into_value();
+    assert_eq!(HashMap::<String, Vec<f64>>::from_value(&val), Ok(map));
+}
@@ -2,7 +2,14 @@
 
 use dy::*;
 use std::env;
+use std::fs;
+use std::future::Future;
 use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread;
+use std::time::{Duration, SystemTime};
 
 fn build_cargo(current_dir: &String, is_release: bool) {
     let mut command = Command::new("cargo");
@@ -16,14 +23,35 @@ fn build_cargo(current_dir: &String, is_release: bool) {
     assert!(status.success());
 }
 
-#[test]
-fn dll_test() {
+fn build_dll_test() -> String {
     let profile = env!("PROFILE");
     let is_release = profile == "release";
     let crate_path = format!("{}/tests/dll_test", env!("CARGO_MANIFEST_DIR"));
     build_cargo(&crate_path, is_release);
+    format!("{}/target/{}", crate_path, profile)
+}
 
-    let m = Module::new("dll_test", &[&format!("{}/target/{}", crate_path, profile)]).unwrap();
+/// Returns the file name cargo gives to a `cdylib` on the target OS
+fn dll_file_name(name: &str) -> String {
+    if cfg!(windows) {
+        format!("{}.dll", name)
+    } else if cfg!(target_os = "macos") {
+        format!("lib{}.dylib", name)
+    } else {
+        format!("lib{}.so", name)
+    }
+}
+
+#[test]
+fn dll_test() {
+    let target_dir = build_dll_test();
+
+    let m = Module::new("dll_test", &[&target_dir]).unwrap();
+    assert!(ModuleLoader::new()
+        .dir(&target_dir)
+        .allow_unversioned(false)
+        .load("dll_test")
+        .is_ok());
     let f = m.get_fn("multiply_two_only_numbers").unwrap();
     let args = vec![
         Value::new_int(5),
@@ -41,3 +69,940 @@ fn dll_test() {
         }
     }
 }
+
+#[test]
+fn custom_naming_test() {
+    let target_dir = build_dll_test();
+    let built = dll_file_name("dll_test");
+
+    let dir = env::temp_dir().join(format!("dy_custom_naming_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::copy(
+        format!("{}/{}", target_dir, built),
+        dir.join("plug_dll_test.plugin.3"),
+    )
+    .unwrap();
+
+    let naming = Naming::new("plug_", ".plugin").version("3");
+    let dir = dir.to_str().unwrap();
+    assert!(Module::new("dll_test", &[dir]).is_none());
+
+    let m = Module::find("dll_test", &[dir], &naming, false).unwrap();
+    assert!(m.get_fn("multiply_two_only_numbers").is_some());
+    drop(m);
+
+    let m = Module::open(format!("{}/plug_dll_test.plugin.3", dir)).unwrap();
+    assert!(m.get_fn("multiply_two_only_numbers").is_some());
+    drop(m);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn manifest_test() {
+    let target_dir = build_dll_test();
+
+    let m = Module::new("dll_test", &[&target_dir]).unwrap();
+    let functions = m.functions();
+    let names: Vec<&str> = functions.iter().map(|info| info.name.as_str()).collect();
+    let mut sorted = names.clone();
+    sorted.sort_unstable();
+    assert_eq!(names, sorted);
+    let find = |name: &str| functions.iter().find(|info| info.name == name).unwrap();
+
+    let info = find("multiply_two_only_numbers");
+    assert_eq!(info.arity, None);
+    assert_eq!(
+        info.signature.as_deref(),
+        Some("(args: Vec<Borrowed<'_>>) -> Owned")
+    );
+    assert_eq!(
+        info.doc.as_deref(),
+        Some("Doubles every integer and floating point number")
+    );
+    let info = find("scale");
+    assert_eq!(info.arity, Some(2));
+    assert_eq!(
+        info.signature.as_deref(),
+        Some("(v: Vec<f64>, k: f64) -> Vec<f64>")
+    );
+    assert_eq!(
+        info.doc.as_deref(),
+        Some("Scales every element of `v` by `k`")
+    );
+    assert!(find("first_or_panic").doc.is_none());
+}
+
+#[test]
+fn error_propagation_test() {
+    let target_dir = build_dll_test();
+
+    let m = Module::new("dll_test", &[&target_dir]).unwrap();
+    let f = m.get_fn("checked_sqrt").unwrap();
+    let res = f.try_call(vec![Value::new_float(6.25)]).unwrap();
+    assert_eq!(res.as_float().unwrap().get(), 2.5);
+
+    match f.try_call(vec![Value::new_float(-1.0)]) {
+        Err(CallError::Error { message, location }) => {
+            assert_eq!(message, "expected a non-negative number");
+            assert!(location.unwrap().contains("lib.rs"));
+        }
+        _ => panic!("Invalid result"),
+    }
+
+    let f = m.get_fn("always_panic").unwrap();
+    match f.try_call(vec![Value::new_str("plugin failure")]) {
+        Err(CallError::Panic { message, location }) => {
+            assert_eq!(message, "plugin failure");
+            assert!(location.unwrap().contains("lib.rs"));
+        }
+        _ => panic!("Invalid result"),
+    }
+
+    // `#[export]` catches panics too
+    let f = m.get_fn("first_or_panic").unwrap();
+    let res = f.try_call(vec![Value::new_int(7)]).unwrap();
+    assert_eq!(res.as_int().unwrap().get(), 7);
+    match f.try_call(vec![]) {
+        Err(CallError::Panic { message, .. }) => assert_eq!(message, "expected an argument"),
+        _ => panic!("Invalid result"),
+    }
+}
+
+#[test]
+fn typed_export_test() {
+    let target_dir = build_dll_test();
+
+    let m = Module::new("dll_test", &[&target_dir]).unwrap();
+    let f = m.get_fn("scale").unwrap();
+    let res = f
+        .try_call(vec![
+            Value::new_float_arr(&[1.0, 2.5]),
+            Value::new_float(2.0),
+        ])
+        .unwrap();
+    assert_eq!(res.as_float_arr().unwrap().data(), &[2.0, 5.0]);
+
+    let err = f.try_call(vec![Value::new_float(2.0)]).unwrap_err();
+    assert!(matches!(err, CallError::Argument { .. }));
+    assert_eq!(err.message(), "expected 2 arguments, found 1");
+
+    let err = f
+        .try_call(vec![Value::new_float_arr(&[1.0]), Value::new_str("2")])
+        .unwrap_err();
+    assert_eq!(err.message(), "argument `k`: expected Float, found Str");
+
+    let f = m.get_fn("repeat").unwrap();
+    let res = f
+        .try_call(vec![Value::new_str("ab"), Value::new_int(3)])
+        .unwrap();
+    assert_eq!(res.as_str().unwrap().get(), "ababab");
+    let err = f.try_call(vec![Value::new_str("ab")]).unwrap_err();
+    assert_eq!(err.message(), "expected 2 arguments, found 1");
+    let err = f
+        .try_call(vec![Value::new_str("ab"), Value::new_int(-1)])
+        .unwrap_err();
+    assert_eq!(err.message(), "argument `n`: -1 is out of the range of u32");
+
+    let f = m.get_fn("label").unwrap();
+    let res = f
+        .try_call(vec![Value::new_int(3), Value::new_str("ab")])
+        .unwrap();
+    assert_eq!(res.as_str().unwrap().get(), "3: ab");
+    let err = f
+        .try_call(vec![Value::new_int(3), Value::new_int(4)])
+        .unwrap_err();
+    assert_eq!(err.message(), "argument `s`: expected Str, found Int");
+    assert_eq!(m.call_typed::<_, i64>("count_chars", ("héllo",)), Ok(5));
+}
+
+#[test]
+fn typed_import_test() {
+    let target_dir = build_dll_test();
+
+    let m = Module::new("dll_test", &[&target_dir]).unwrap();
+    let f = m.get_fn("scale").unwrap();
+    let res: Vec<f64> = f.call_typed((vec![1.0, 2.5], 2.0)).unwrap();
+    assert_eq!(res, vec![2.0, 5.0]);
+
+    match f.call_typed::<_, String>((vec![1.0], 2.0)) {
+        Err(err @ CallError::Return { .. }) => {
+            assert_eq!(
+                err.to_string(),
+                "unexpected return value: expected Str, found FloatArr"
+            );
+            match err {
+                CallError::Return {
+                    error: ConvertError::Type { expected, found },
+                    ..
+                } => {
+                    assert_eq!(expected, Type::Str);
+                    assert_eq!(found, Type::FloatArr);
+                }
+                _ => panic!("Invalid result"),
+            }
+        }
+        _ => panic!("Invalid result"),
+    }
+
+    let sqrt = m.get_typed_fn::<(f64,), f64>("checked_sqrt").unwrap();
+    assert_eq!(sqrt.call((6.25,)), Ok(2.5));
+    assert!(sqrt.call((-1.0,)).is_err());
+}
+
+dy::import_module! {
+    struct DllTest = "dll_test";
+    fn checked_sqrt(x: f64) -> f64;
+    fn scale(v: Vec<f64>, k: f64) -> Vec<f64>;
+    fn always_panic(message: String);
+}
+
+dy::import_module! {
+    struct Incomplete = "dll_test";
+    fn scale(v: Vec<f64>, k: f64) -> Vec<f64>;
+    fn missing_function();
+}
+
+#[test]
+fn import_module_test() {
+    let target_dir = build_dll_test();
+
+    let m = DllTest::new(&[&target_dir]).unwrap();
+    assert_eq!(m.checked_sqrt(6.25), Ok(2.5));
+    assert_eq!(m.scale(vec![1.0, 2.5], 2.0), Ok(vec![2.0, 5.0]));
+    match m.always_panic(String::from("plugin failure")) {
+        Err(CallError::Panic { message, .. }) => assert_eq!(message, "plugin failure"),
+        _ => panic!("Invalid result"),
+    }
+
+    match Incomplete::new(&[&target_dir]) {
+        Err(LoadError::MissingFunctions(names)) => assert_eq!(names, vec!["missing_function"]),
+        _ => panic!("Invalid result"),
+    }
+}
+
+#[test]
+fn owned_function_test() {
+    let target_dir = build_dll_test();
+
+    let f = {
+        let m = SharedModule::new(Module::new("dll_test", &[&target_dir]).unwrap());
+        m.get_fn("checked_sqrt").unwrap()
+    };
+    assert_eq!(f.name(), "checked_sqrt");
+
+    let workers: Vec<_> = (0..4)
+        .map(|i| {
+            let f = f.clone();
+            thread::spawn(move || f.call_typed::<_, f64>((f64::from(i * i),)).unwrap())
+        })
+        .collect();
+    let res: Vec<f64> = workers.into_iter().map(|w| w.join().unwrap()).collect();
+    assert_eq!(res, vec![0.0, 1.0, 2.0, 3.0]);
+}
+
+fn touch(path: &std::path::Path, secs: u64) {
+    fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(SystemTime::now() + Duration::from_secs(secs))
+        .unwrap();
+}
+
+#[test]
+fn reload_test() {
+    let target_dir = build_dll_test();
+    let built = dll_file_name("dll_test");
+
+    let dir = env::temp_dir().join(format!("dy_reload_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(&built);
+    fs::copy(format!("{}/{}", target_dir, built), &path).unwrap();
+
+    let m = ReloadableModule::new(&path).unwrap();
+    let reloads = Arc::new(AtomicUsize::new(0));
+    let errors = Arc::new(AtomicUsize::new(0));
+    {
+        let reloads = reloads.clone();
+        m.on_reload(move |_| {
+            reloads.fetch_add(1, Ordering::SeqCst);
+        });
+        let errors = errors.clone();
+        m.on_error(move |_| {
+            errors.fetch_add(1, Ordering::SeqCst);
+        });
+    }
+
+    let f = m.get_fn("checked_sqrt").unwrap();
+    let old = f.current();
+    assert_eq!(m.check(), Ok(false));
+
+    touch(&path, 10);
+    assert_eq!(m.check(), Ok(true));
+    assert_eq!(m.version(), 1);
+    assert_eq!(reloads.load(Ordering::SeqCst), 1);
+    assert_eq!(f.call_typed((6.25,)), Ok(2.5));
+    assert_eq!(old.call_typed((6.25,)), Ok(2.5));
+
+    fs::write(&path, b"not a library").unwrap();
+    touch(&path, 20);
+    assert!(m.check().is_err());
+    assert_eq!(m.check(), Ok(false));
+    assert_eq!(errors.load(Ordering::SeqCst), 1);
+    assert_eq!(m.version(), 1);
+    assert_eq!(f.call_typed((6.25,)), Ok(2.5));
+    drop(f);
+    drop(old);
+    drop(m);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn registry_test() {
+    use dy::registry::*;
+
+    let target_dir = build_dll_test();
+    let mut registry = PluginRegistry::new();
+    let report = registry.scan(&target_dir).unwrap();
+    assert_eq!(report.loaded, vec!["dll_test"]);
+    assert!(report.failed.is_empty());
+
+    let functions = registry.functions();
+    assert!(functions
+        .iter()
+        .any(|info| info.name == "dll_test.checked_sqrt"));
+
+    let res = registry
+        .call("dll_test.checked_sqrt", vec![Value::new_float(4.0)])
+        .unwrap();
+    assert_eq!(res.as_float().unwrap().get(), 2.0);
+    assert!(registry
+        .get_fn("dll_test.multiply_two_only_numbers")
+        .is_ok());
+    match registry.get_fn("dll_test.missing") {
+        Err(RegistryError::UnknownFunction(name)) => assert_eq!(name, "dll_test.missing"),
+        _ => panic!("Expected an unknown function"),
+    }
+    assert!(matches!(
+        registry.get_fn("checked_sqrt"),
+        Err(RegistryError::InvalidName(_))
+    ));
+
+    let m = Module::new("dll_test", &[&target_dir]).unwrap();
+    assert!(matches!(
+        registry.add("dll_test", m),
+        Err(RegistryError::Collision(_))
+    ));
+
+    registry.disable("dll_test").unwrap();
+    assert!(!registry.is_enabled("dll_test"));
+    assert!(registry.functions().is_empty());
+    assert!(matches!(
+        registry.call("dll_test.checked_sqrt", vec![Value::new_float(4.0)]),
+        Err(RegistryError::Disabled(_))
+    ));
+    registry.enable("dll_test").unwrap();
+
+    let f = registry.get_fn("dll_test.checked_sqrt").unwrap();
+    registry.unload("dll_test").unwrap();
+    assert!(registry.modules().is_empty());
+    assert!(matches!(
+        registry.get_fn("dll_test.checked_sqrt"),
+        Err(RegistryError::UnknownModule(_))
+    ));
+    // the DLL stays loaded while a function of it is alive
+    let res = f.call_typed::<_, f64>((9.0,)).unwrap();
+    assert_eq!(res, 3.0);
+}
+
+#[test]
+fn remote_module_test() {
+    use dy::remote::*;
+
+    let target_dir = build_dll_test();
+    env::set_var(HOST_VAR, env!("CARGO_BIN_EXE_dy-host"));
+
+    let m = RemoteModule::new("dll_test", &[&target_dir]).unwrap();
+    assert!(m.functions().iter().any(|info| info.name == "checked_sqrt"));
+    assert!(m.get_fn("missing_function").unwrap().is_none());
+    assert!(ModuleApi::call(&m, "missing_function", vec![]).is_none());
+    let res = ModuleApi::call(&m, "checked_sqrt", vec![Value::new_float(4.0)]);
+    assert_eq!(res.unwrap().as_float().unwrap().get(), 2.0);
+
+    let f = m.get_fn("checked_sqrt").unwrap().unwrap();
+    assert_eq!(f.call_typed((6.25,)), Ok(2.5));
+    match f.call_typed::<_, f64>((-1.0,)) {
+        Err(CallError::Error { message, .. }) => {
+            assert_eq!(message, "expected a non-negative number")
+        }
+        _ => panic!("Invalid result"),
+    }
+    let res = m
+        .get_fn("multiply_two_only_numbers")
+        .unwrap()
+        .unwrap()
+        .call(vec![Value::new_int(5), Value::new_str("Hello")]);
+    let res = res.as_arr().unwrap();
+    assert_eq!(res.at(0).unwrap().as_int().unwrap().get(), 10);
+    assert_eq!(res.at(1).unwrap().as_str().unwrap().get(), "Hello");
+
+    let crash = m.get_fn("crash").unwrap().unwrap();
+    match crash.call_typed::<_, ()>(()) {
+        Err(CallError::Error { message, .. }) => assert!(message.contains("crashed")),
+        _ => panic!("Invalid result"),
+    }
+    assert_eq!(m.restarts(), 0);
+    assert_eq!(f.call_typed((9.0,)), Ok(3.0));
+    assert_eq!(m.restarts(), 1);
+
+    // a hung host is killed and restarted by the next request
+    let timeout = Duration::from_millis(100);
+    m.set_timeout(Some(timeout));
+    let sleep = m.get_fn("sleep_ms").unwrap().unwrap();
+    let start = SystemTime::now();
+    let res = sleep.call_typed::<_, ()>((10_000,));
+    assert_eq!(res, Err(CallError::timeout(timeout)));
+    assert!(start.elapsed().unwrap() < Duration::from_secs(5));
+    assert_eq!(f.call_typed((9.0,)), Ok(3.0));
+    assert_eq!(m.restarts(), 2);
+    m.set_timeout(None);
+
+    // a host which cannot be restarted is told apart from a missing function
+    let dir = env::temp_dir().join(format!("dy_remote_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let built = dll_file_name("dll_test");
+    let path = dir.join(&built);
+    fs::copy(format!("{}/{}", target_dir, built), &path).unwrap();
+    let copy = RemoteModule::open(&path).unwrap();
+    let crash = copy.get_fn("crash").unwrap().unwrap();
+    assert!(crash.call_typed::<_, ()>(()).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+    match copy.get_fn("checked_sqrt") {
+        Err(err) => assert!(err.message().starts_with("could not restart")),
+        _ => panic!("Expected a connection error"),
+    }
+
+    match RemoteModule::with_loader(
+        "surely_missing_module",
+        &ModuleLoader::new().dir(&target_dir),
+    ) {
+        Err(LoadError::NotFound { name, tried }) => {
+            assert_eq!(name, "surely_missing_module");
+            assert!(!tried.is_empty());
+        }
+        _ => panic!("Invalid result"),
+    }
+}
+
+#[test]
+fn timeout_test() {
+    let target_dir = build_dll_test();
+    let m = SharedModule::new(Module::new("dll_test", &[&target_dir]).unwrap());
+
+    let f = m.get_fn("checked_sqrt").unwrap();
+    let res = f.call_with_timeout(vec![Value::new_float(6.25)], Duration::from_secs(5));
+    assert_eq!(res.unwrap().as_float().unwrap().get(), 2.5);
+
+    let f = m.get_fn("wait_for_cancel").unwrap();
+    let token = CancelToken::new();
+    let timeout = Duration::from_millis(20);
+    let res = f.call_with_timeout(vec![Value::new_int(500), token.to_value()], timeout);
+    assert_eq!(res.unwrap_err(), CallError::timeout(timeout));
+    assert!(!token.is_cancelled());
+
+    // a token dropped by the caller stays alive until the call returns
+    let dropped = CancelToken::new();
+    let res = f.call_with_timeout(vec![Value::new_int(50), dropped.to_value()], timeout);
+    drop(dropped);
+    assert_eq!(res.unwrap_err(), CallError::timeout(timeout));
+
+    let forged = Value::new_map(vec![("$dy_cancel", Value::new_int(i64::MAX))]);
+    let res = f.call_with_timeout(vec![Value::new_int(1), forged], Duration::from_secs(5));
+    assert!(matches!(res.unwrap_err(), CallError::Argument { .. }));
+
+    let res = f.call_cancellable(vec![Value::new_int(10_000)], &token, timeout);
+    assert_eq!(res.unwrap_err(), CallError::timeout(timeout));
+    assert!(token.is_cancelled());
+    let res = f.call_cancellable(vec![Value::new_int(10_000)], &token, Duration::from_secs(5));
+    assert!(res.unwrap().as_bool().unwrap().get());
+}
+
+struct ThreadWaker(thread::Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+fn block_on<F: Future>(fut: F) -> F::Output {
+    let mut fut = Box::pin(fut);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match fut.as_mut().poll(&mut cx) {
+            Poll::Ready(rtn) => return rtn,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+fn assert_send<T: Send>(val: T) -> T {
+    val
+}
+
+#[test]
+fn async_call_test() {
+    let target_dir = build_dll_test();
+    let m = SharedModule::new(Module::new("dll_test", &[&target_dir]).unwrap());
+    let f = m.get_fn("checked_sqrt").unwrap();
+
+    let futures: Vec<_> = (0..4)
+        .map(|i| assert_send(f.call_async(vec![Value::new_float(f64::from(i * i))])))
+        .collect();
+    let res: Vec<f64> = block_on(async {
+        let mut res = Vec::new();
+        for fut in futures {
+            res.push(fut.await.as_float().unwrap().get());
+        }
+        res
+    });
+    assert_eq!(res, vec![0.0, 1.0, 2.0, 3.0]);
+
+    let fut = assert_send(f.try_call_async(vec![Value::new_float(-1.0)]));
+    match block_on(fut) {
+        Err(CallError::Error { message, .. }) => {
+            assert_eq!(message, "expected a non-negative number")
+        }
+        _ => panic!("Invalid result"),
+    }
+}
+
+struct RecordingHook {
+    calls: std::sync::Mutex<Vec<(String, usize, bool)>>,
+}
+
+impl CallHook for RecordingHook {
+    fn before(&self, name: &str, args: &[Borrowed<'_>]) {
+        let mut calls = self.calls.lock().unwrap();
+        calls.push((String::from(name), args.len(), false));
+    }
+
+    fn after(&self, name: &str, _args: &[Borrowed<'_>], _rtn: &Value, _elapsed: Duration) {
+        let mut calls = self.calls.lock().unwrap();
+        let last = calls.last_mut().unwrap();
+        assert_eq!(last.0, name);
+        last.2 = true;
+    }
+}
+
+#[test]
+fn hook_test() {
+    let target_dir = build_dll_test();
+
+    let metrics = Arc::new(MetricsHook::new());
+    let recording = Arc::new(RecordingHook {
+        calls: std::sync::Mutex::new(Vec::new()),
+    });
+    let mut m = Module::new("dll_test", &[&target_dir]).unwrap();
+    m.add_hook(metrics.clone());
+    m.add_hook(recording.clone());
+
+    let f = m.get_fn("checked_sqrt").unwrap();
+    assert_eq!(f.name(), "checked_sqrt");
+    assert!(f.try_call(vec![Value::new_float(6.25)]).is_ok());
+    assert!(f.try_call(vec![Value::new_float(-1.0)]).is_err());
+
+    let m = SharedModule::new(m);
+    let f = m.get_fn("scale").unwrap();
+    let res: Vec<f64> = f.call_typed((vec![1.0], 2.0)).unwrap();
+    assert_eq!(res, vec![2.0]);
+
+    let stats = metrics.get("checked_sqrt").unwrap();
+    assert_eq!((stats.calls, stats.errors), (2, 1));
+    assert_eq!(stats.histogram.iter().sum::<u64>(), 2);
+    assert!(stats.max <= stats.total);
+    assert_eq!(metrics.get("scale").unwrap().calls, 1);
+    let stats = CallStats {
+        calls: 3,
+        total: Duration::from_nanos(10),
+        ..CallStats::default()
+    };
+    assert_eq!(stats.mean(), Duration::from_nanos(3));
+    assert_eq!(CallStats::default().mean(), Duration::ZERO);
+    assert!(metrics.get("crash").is_none());
+
+    let calls = recording.calls.lock().unwrap();
+    assert_eq!(
+        *calls,
+        vec![
+            (String::from("checked_sqrt"), 1, true),
+            (String::from("checked_sqrt"), 1, true),
+            (String::from("scale"), 2, true),
+        ]
+    );
+    drop(calls);
+
+    metrics.reset();
+    assert!(metrics.snapshot().is_empty());
+}
+
+#[test]
+fn record_replay_test() {
+    let target_dir = build_dll_test();
+    let path = env::temp_dir().join(format!("dy_record_{}.bin", std::process::id()));
+
+    let recorder = Recorder::create(&path).unwrap();
+    let mut m = Module::new("dll_test", &[&target_dir]).unwrap();
+    m.add_hook(recorder.clone());
+    let f = m.get_fn("checked_sqrt").unwrap();
+    assert_eq!(f.call_typed((6.25,)), Ok(2.5));
+    assert!(f.try_call(vec![Value::new_float(-1.0)]).is_err());
+
+    let plain = Module::new("dll_test", &[&target_dir]).unwrap();
+    let f = recorder.wrap(plain.get_fn("scale").unwrap());
+    let res: Vec<f64> = f.call_typed((vec![1.0, 2.5], 2.0)).unwrap();
+    assert_eq!(res, vec![2.0, 5.0]);
+    assert!(recorder.take_error().is_none());
+    drop(recorder);
+    drop(m);
+
+    let replay = ReplayModule::open(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(replay.len(), 3);
+    let names: Vec<String> = replay
+        .functions()
+        .into_iter()
+        .map(|info| info.name)
+        .collect();
+    assert_eq!(names, vec!["checked_sqrt", "scale"]);
+
+    assert_eq!(replay.call_typed("checked_sqrt", (6.25,)), Ok(2.5));
+    match replay.try_call("checked_sqrt", vec![Value::new_float(-1.0)]) {
+        Err(CallError::Error { message, .. }) => {
+            assert_eq!(message, "expected a non-negative number")
+        }
+        _ => panic!("Invalid result"),
+    }
+    let res: Vec<f64> = replay.call_typed("scale", (vec![1.0, 2.5], 2.0)).unwrap();
+    assert_eq!(res, vec![2.0, 5.0]);
+
+    let err = replay
+        .call_typed::<_, f64>("checked_sqrt", (4.0,))
+        .unwrap_err();
+    assert!(err.message().starts_with("no recording of `checked_sqrt`"));
+    assert!(replay.call("crash", vec![]).is_none());
+}
+
+#[test]
+fn callback_test() {
+    let target_dir = build_dll_test();
+
+    let m = Module::new("dll_test", &[&target_dir]).unwrap();
+    let f = m.get_fn("sum_with_progress").unwrap();
+    let reports = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let next = Callback::new(|args: &[Borrowed<'_>]| args[0].as_int().unwrap().get() * 10);
+    let progress = Callback::new({
+        let reports = reports.clone();
+        move |args: &[Borrowed<'_>]| {
+            let done = args[0].as_int().unwrap().get();
+            reports.lock().unwrap().push(done);
+        }
+    });
+    let res = f
+        .try_call(vec![
+            Value::new_int(3),
+            next.to_value(),
+            progress.to_value(),
+        ])
+        .unwrap();
+    assert_eq!(res.as_int().unwrap().get(), 30);
+    assert_eq!(*reports.lock().unwrap(), vec![1, 2, 3]);
+
+    let failing = Callback::new(|_: &[Borrowed<'_>]| -> Result<i64, CallError> {
+        Err(CallError::new("no more data"))
+    });
+    let err = f
+        .try_call(vec![
+            Value::new_int(3),
+            failing.to_value(),
+            progress.to_value(),
+        ])
+        .unwrap_err();
+    assert_eq!(err.message(), "no more data");
+
+    // values only name callbacks which are alive
+    let forged = Value::new_map(vec![("$dy_callback", Value::new_int(i64::MAX))]);
+    let dropped = next.to_value();
+    drop(next);
+    for arg in [forged, dropped] {
+        let err = f
+            .try_call(vec![Value::new_int(3), arg, progress.to_value()])
+            .unwrap_err();
+        assert_eq!(err.message(), "argument `next`: the callback is not alive");
+    }
+
+    let panicking = Callback::new(|_: &[Borrowed<'_>]| -> i64 { panic!("host failure") });
+    match panicking.call_typed::<_, i64>(()) {
+        Err(CallError::Panic { message, .. }) => assert_eq!(message, "host failure"),
+        _ => panic!("Invalid result"),
+    }
+}
+
+struct Unrelated;
+
+unsafe impl HandleType for Unrelated {
+    const TAG: &'static str = "dll_test::Unrelated";
+}
+
+#[test]
+fn handle_test() {
+    let target_dir = build_dll_test();
+
+    let m = Module::new("dll_test", &[&target_dir]).unwrap();
+    let open = m.get_typed_fn::<(i64,), Handle>("open_counter").unwrap();
+    let increment = m.get_fn("increment").unwrap();
+    let alive = m.get_typed_fn::<(), i64>("counters_alive").unwrap();
+
+    let counter = open.call((5,)).unwrap();
+    assert_eq!(counter.tag(), "dll_test::Counter");
+    assert!(counter.get::<Unrelated>().is_err());
+    assert_eq!(alive.call(()), Ok(1));
+    for expected in 6..8 {
+        let res = increment.try_call(vec![counter.to_value()]).unwrap();
+        assert_eq!(res.as_int().unwrap().get(), expected);
+    }
+
+    let other = Handle::new(Unrelated);
+    let err = increment.try_call(vec![other.to_value()]).unwrap_err();
+    assert_eq!(
+        err.message(),
+        "expected a handle of `dll_test::Counter`, found `dll_test::Unrelated`"
+    );
+
+    let copy = counter.clone();
+    drop(counter);
+    assert_eq!(alive.call(()), Ok(1));
+    drop(copy);
+    assert_eq!(alive.call(()), Ok(0));
+
+    // the reference moved into a value is taken over once; later reads take their own
+    let open_raw = m.get_fn("open_counter").unwrap();
+    let val = open_raw.call(vec![Value::new_int(0)]);
+    let first = Handle::from_value(&val).unwrap();
+    let second = Handle::from_value(&val).unwrap();
+    assert!(Handle::ptr_eq(&first, &second));
+    drop(val);
+    drop(first);
+    assert_eq!(alive.call(()), Ok(1));
+    drop(second);
+    assert_eq!(alive.call(()), Ok(0));
+
+    let forged = Value::new_map(vec![
+        ("$dy_handle", Value::new_int(i64::MAX)),
+        ("tag", Value::new_str("dll_test::Counter")),
+    ]);
+    let err = increment.try_call(vec![forged]).unwrap_err();
+    assert_eq!(err.message(), "argument `counter`: the handle is not alive");
+}
+
+#[test]
+fn handle_keeps_dll_test() {
+    use dy::registry::*;
+
+    let target_dir = build_dll_test();
+    let dir = env::temp_dir().join(format!("dy_keep_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let built = dll_file_name("dll_test");
+    fs::copy(format!("{}/{}", target_dir, built), dir.join(&built)).unwrap();
+
+    let mut registry = PluginRegistry::new();
+    registry.scan(&dir).unwrap();
+    let val = registry
+        .call("dll_test.open_counter", vec![Value::new_int(0)])
+        .unwrap();
+    let counter = Handle::from_value(&val).unwrap();
+    drop(val);
+    registry.unload("dll_test").unwrap();
+    drop(registry);
+    // the tag and the drop of the counter are in the DLL, which stays loaded
+    assert_eq!(counter.tag(), "dll_test::Counter");
+
+    // loading the DLL again finds the instance the counter keeps loaded
+    let m = Module::new("dll_test", &[dir.to_str().unwrap()]).unwrap();
+    let alive = m.get_typed_fn::<(), i64>("counters_alive").unwrap();
+    assert_eq!(alive.call(()), Ok(1));
+    drop(counter);
+    assert_eq!(alive.call(()), Ok(0));
+    drop(alive);
+    drop(m);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn lifecycle_test() {
+    let target_dir = build_dll_test();
+    let marker = env::temp_dir().join(format!("dy_shutdown_{}", std::process::id()));
+    let config = Value::new_map(vec![("marker", Value::new_str(marker.to_str().unwrap()))]);
+
+    let m = Module::new("dll_test", &[&target_dir]).unwrap();
+    let err = m.initialize(&Value::new_null()).unwrap_err();
+    assert_eq!(err.message(), "expected a marker path");
+    assert!(!m.is_initialized());
+    drop(m);
+    assert!(!marker.exists());
+
+    let m = Module::new("dll_test", &[&target_dir]).unwrap();
+    m.initialize(&config).unwrap();
+    assert!(m.is_initialized());
+    let err = m.initialize(&config).unwrap_err();
+    assert_eq!(err.message(), "the module is already initialized");
+    drop(m);
+    assert_eq!(fs::read_to_string(&marker).unwrap(), "shut down");
+    fs::remove_file(&marker).unwrap();
+
+    let m = Module::new("dll_test", &[&target_dir]).unwrap();
+    m.initialize(&config).unwrap();
+    m.shutdown().unwrap();
+    assert!(!m.is_initialized());
+    fs::remove_file(&marker).unwrap();
+    drop(m);
+    assert!(!marker.exists());
+
+    let dir = env::temp_dir().join(format!("dy_lifecycle_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let built = dll_file_name("dll_test");
+    let path = dir.join(&built);
+    fs::copy(format!("{}/{}", target_dir, built), &path).unwrap();
+    let config = Value::new_map(vec![
+        ("marker", Value::new_str(marker.to_str().unwrap())),
+        ("delay_ms", Value::new_int(500)),
+    ]);
+    let m = ReloadableModule::new(&path).unwrap();
+    m.initialize(&config).unwrap();
+    let reload = {
+        let m = m.clone();
+        thread::spawn(move || m.reload())
+    };
+    thread::sleep(Duration::from_millis(100));
+    // waits for the new version, which is initializing
+    let f = m.get_fn("checked_sqrt").unwrap();
+    assert_eq!(m.version(), 1);
+    reload.join().unwrap().unwrap();
+    assert!(m.current().is_initialized());
+    assert_eq!(f.call_typed((6.25,)), Ok(2.5));
+    drop(f);
+    // the first version is shut down once replaced
+    assert_eq!(fs::read_to_string(&marker).unwrap(), "shut down");
+    fs::remove_file(&marker).unwrap();
+    drop(m);
+    assert_eq!(fs::read_to_string(&marker).unwrap(), "shut down");
+    fs::remove_file(&marker).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn cli_test() {
+    let target_dir = build_dll_test();
+    let built = dll_file_name("dll_test");
+    let lib = format!("{}/{}", target_dir, built);
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_dy"))
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["list", &lib]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Returns the square root of a non-negative number"));
+
+    let output = run(&["call", &lib, "scale", "f[1, 2.5]", "2.0"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "f[2.0, 5.0]\n");
+
+    let output = run(&["call", &lib, "checked_sqrt", "-1.0"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("expected a non-negative number"));
+
+    let output = run(&["call", &lib, "checked_sqrt", "{"]);
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = run(&["--require-versioned", "call", &lib, "checked_sqrt", "4.0"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout, "2.0\n");
+
+    let output = run(&["--config", "null", "list", &lib]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("expected a marker path"));
+
+    let marker = env::temp_dir().join(format!("dy_cli_{}", std::process::id()));
+    let config = text::to_string(&Value::new_map(vec![(
+        "marker",
+        Value::new_str(marker.to_str().unwrap()),
+    )]));
+    let output = run(&["--config", &config, "call", &lib, "repeat", "\"a\"", "2"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "\"aa\"\n");
+    assert_eq!(fs::read_to_string(&marker).unwrap(), "shut down");
+    fs::remove_file(&marker).unwrap();
+}
+
+#[cfg(feature = "repl")]
+#[test]
+fn repl_test() {
+    use std::io::Write;
+
+    let target_dir = build_dll_test();
+    let built = dll_file_name("dll_test");
+    let lib = format!("{}/{}", target_dir, built);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_dy"))
+        .args(["repl", &lib])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let input = "scale(f[1, 2.5], 2.0)\n\
+                 dll_test.scale($1, 0.5)\n\
+                 [$2, {k: \"v\"}]\n\
+                 checked_sqrt(-1.0)\n\
+                 missing()\n";
+    // a versioned file name names the same module
+    let dir = env::temp_dir().join(format!("dy_repl_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let versioned = dir.join(format!("{}.1", built));
+    fs::copy(&lib, &versioned).unwrap();
+    let input = format!("{}:load {}\n", input, versioned.display());
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout,
+        "$1 = f[2.0, 5.0]  // FloatArr[2]\n\
+         $2 = f[1.0, 2.5]  // FloatArr[2]\n\
+         $3 = [  // Arr[2]\n  \
+           f[1.0, 2.5],  // FloatArr[2]\n  \
+           {  // Map[1]\n    \
+             \"k\": \"v\"  // Str\n  \
+           }\n\
+         ]\n"
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("error: expected a non-negative number"));
+    assert!(stderr.contains("no module exports `missing`"));
+    assert!(stderr.contains("a module named `dll_test` is already loaded"));
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -1,5 +1,48 @@
 use dy::*;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
 
+static COUNTERS_ALIVE: AtomicI64 = AtomicI64::new(0);
+static MARKER: Mutex<Option<String>> = Mutex::new(None);
+
+#[dy::init]
+fn init(config: &Value) -> Result<(), CallError> {
+    let marker = config
+        .as_map()
+        .and_then(|map| map.at("marker"))
+        .and_then(|pair| pair.get_val().as_str().map(|s| s.get()))
+        .ok_or_else(|| CallError::new("expected a marker path"))?;
+    *MARKER.lock().unwrap() = Some(marker);
+    // lets tests call the host while a reload is in progress
+    if let Some(delay) = config.as_map().and_then(|map| map.at("delay_ms")) {
+        let delay = u32::from_value(delay.get_val()).map_err(|err| err.to_string())?;
+        std::thread::sleep(std::time::Duration::from_millis(u64::from(delay)));
+    }
+    Ok(())
+}
+
+#[dy::shutdown]
+fn shutdown() {
+    if let Some(marker) = MARKER.lock().unwrap().take() {
+        std::fs::write(marker, "shut down").unwrap();
+    }
+}
+
+struct Counter {
+    value: Mutex<i64>,
+}
+
+unsafe impl HandleType for Counter {
+    const TAG: &'static str = "dll_test::Counter";
+}
+
+impl Drop for Counter {
+    fn drop(&mut self) {
+        COUNTERS_ALIVE.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Doubles every integer and floating point number
 #[export]
 pub fn multiply_two_only_numbers(args: Vec<Borrowed<'_>>) -> Owned {
     Value::new_arr(
@@ -12,3 +55,95 @@ pub fn multiply_two_only_numbers(args: Vec<Borrowed<'_>>) -> Owned {
             .collect(),
     )
 }
+
+/// Repeats `s` `n` times
+#[export]
+pub fn repeat(s: String, n: u32) -> String {
+    s.repeat(n as usize)
+}
+
+/// Prefixes `s` with `n`, borrowing `s` from the host
+#[export]
+pub fn label(n: i64, s: &str) -> String {
+    format!("{}: {}", n, s)
+}
+
+#[export]
+pub fn first_or_panic(args: Vec<Borrowed<'_>>) -> Owned {
+    args.first().expect("expected an argument").copy()
+}
+
+dy::exported! {
+    /// Returns the square root of a non-negative number
+    pub fn checked_sqrt(x: f64) -> Result<f64, CallError> {
+        if x >= 0.0 {
+            Ok(x.sqrt())
+        } else {
+            Err(CallError::new("expected a non-negative number"))
+        }
+    }
+
+    /// Panics with the given message
+    pub fn always_panic(message: String) {
+        panic!("{}", message)
+    }
+
+    /// Counts the characters of `s`
+    pub fn count_chars(s: &str) -> usize {
+        s.chars().count()
+    }
+
+    /// Scales every element of `v` by `k`
+    pub fn scale(v: Vec<f64>, k: f64) -> Vec<f64> {
+        v.into_iter().map(|x| x * k).collect()
+    }
+
+    pub fn crash() {
+        std::process::abort()
+    }
+
+    /// Sleeps for `ms` milliseconds
+    pub fn sleep_ms(ms: u32) {
+        std::thread::sleep(std::time::Duration::from_millis(u64::from(ms)))
+    }
+
+    pub fn wait_for_cancel(limit_ms: u32, cancel: CancelToken) -> bool {
+        for _ in 0..limit_ms {
+            if cancel.is_cancelled() {
+                return true;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        false
+    }
+
+    pub fn sum_with_progress(
+        count: u32,
+        next: Callback,
+        progress: Callback,
+    ) -> Result<i64, CallError> {
+        let mut sum = 0;
+        for i in 0..count {
+            sum += next.call_typed::<_, i64>((i,))?;
+            progress.call_typed::<_, ()>((i + 1, count))?;
+        }
+        Ok(sum)
+    }
+
+    pub fn open_counter(start: i64) -> Handle {
+        COUNTERS_ALIVE.fetch_add(1, Ordering::SeqCst);
+        Handle::new(Counter {
+            value: Mutex::new(start),
+        })
+    }
+
+    pub fn increment(counter: Handle) -> Result<i64, CallError> {
+        let mut value = counter.get::<Counter>()?.value.lock().unwrap();
+        *value += 1;
+        Ok(*value)
+    }
+
+    pub fn counters_alive() -> i64 {
+        COUNTERS_ALIVE.load(Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,55 @@
+use dy::*;
+
+fn total_price<M: ModuleApi>(m: &M, items: &[(&str, f64)]) -> Result<f64, CallError> {
+    let mut total = 0.0;
+    for (name, quantity) in items {
+        let price: f64 = m.call_typed("price", (String::from(*name),))?;
+        total += price * quantity;
+    }
+    Ok(total)
+}
+
+#[test]
+fn mock_module_test() {
+    let m = MockModule::new()
+        .function("price", |args| match args[0].as_str().map(|s| s.get()) {
+            Some(name) if name == "apple" => Value::new_float(0.5),
+            Some(_) => CallError::new("unknown item").to_value(),
+            None => Value::new_null(),
+        })
+        .returning("version", Value::new_int(3))
+        .expect("price", 2);
+
+    assert!(m.has_fn("price"));
+    assert!(!m.has_fn("missing"));
+    let names: Vec<String> = m.functions().into_iter().map(|info| info.name).collect();
+    assert_eq!(names, vec!["price", "version"]);
+
+    assert_eq!(total_price(&m, &[("apple", 4.0)]), Ok(2.0));
+    let err = total_price(&m, &[("pear", 1.0)]).unwrap_err();
+    assert_eq!(err.message(), "unknown item");
+    assert_eq!(m.call_typed::<_, i64>("version", ()), Ok(3));
+    assert_eq!(m.call_typed::<_, i64>("version", ()), Ok(3));
+
+    assert_eq!(m.call_count("price"), 2);
+    m.assert_called_with("price", &[Value::new_str("pear")]);
+    let calls = m.calls_to("price");
+    assert_eq!(calls[0][0].as_str().unwrap().get(), "apple");
+    m.verify();
+
+    let err = m.try_call("missing", vec![]).unwrap_err();
+    assert_eq!(err.message(), "no function named `missing`");
+    let calls = m.take_calls();
+    assert_eq!(calls.len(), 5);
+    assert_eq!(calls[4].name, "missing");
+    assert_eq!(m.call_count("price"), 0);
+}
+
+#[test]
+#[should_panic(expected = "`price` was called 0 times, expected 1")]
+fn mock_verify_test() {
+    MockModule::new()
+        .returning("price", Value::new_float(1.0))
+        .expect("price", 1)
+        .verify();
+}
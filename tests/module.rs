@@ -0,0 +1,102 @@
+#![cfg(feature = "import")]
+
+use dy::*;
+
+#[test]
+fn naming_test() {
+    let naming = Naming::new("lib", ".so").suffix(".plugin").version("1");
+    assert_eq!(
+        naming.file_names("foo"),
+        vec!["libfoo.so.1", "libfoo.so", "libfoo.plugin.1", "libfoo.plugin"]
+    );
+    assert_eq!(naming.module_name("libfoo.so.1"), Some(String::from("foo")));
+    assert_eq!(naming.module_name("libfoo.plugin"), Some(String::from("foo")));
+    assert_eq!(naming.module_name("libfoo.so.2"), None);
+    assert_eq!(naming.module_name("foo.so"), None);
+    assert_eq!(naming.module_name("lib.so"), None);
+}
+
+#[test]
+fn missing_module_test() {
+    assert!(Module::new("surely_missing_module", &["."]).is_none());
+    match Module::try_new("surely_missing_module", &["/"]) {
+        Err(LoadError::NotFound { tried, .. }) => assert!(!tried.is_empty()),
+        _ => panic!("Invalid result"),
+    }
+    assert!(Module::find("surely_missing_module", &["/"], &Naming::platform(), true).is_none());
+    assert!(Module::open("/surely/missing/libmodule.so").is_none());
+}
+
+#[test]
+fn loader_test() {
+    std::env::set_var("DY_TEST_MODULE_PATH", "/opt/a:relative/b:");
+    let loader = ModuleLoader::new()
+        .dir("/opt/first")
+        .env("DY_TEST_MODULE_PATH")
+        .env("DY_TEST_UNSET_MODULE_PATH")
+        .naming(Naming::new("lib", ".so"));
+    let cwd = std::env::current_dir().unwrap();
+    assert_eq!(
+        loader.search_paths(),
+        &[
+            std::path::PathBuf::from("/opt/first"),
+            std::path::PathBuf::from("/opt/a"),
+            cwd.join("relative/b"),
+        ]
+    );
+
+    match loader.load("surely_missing_module") {
+        Err(LoadError::NotFound { name, tried }) => {
+            assert_eq!(name, "surely_missing_module");
+            assert_eq!(
+                tried,
+                vec![
+                    std::path::PathBuf::from("/opt/first/libsurely_missing_module.so"),
+                    std::path::PathBuf::from("/opt/a/libsurely_missing_module.so"),
+                    cwd.join("relative/b/libsurely_missing_module.so"),
+                ]
+            );
+        }
+        _ => panic!("Invalid result"),
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn unversioned_module_test() {
+    let loader = ModuleLoader::new().system_search(true);
+    assert!(loader.allows_unversioned());
+    assert!(loader.open("libc.so.6").is_ok());
+    assert!(Module::find("c", &[], &Naming::new("lib", ".so").version("6"), true).is_some());
+
+    let strict = loader.clone().allow_unversioned(false);
+    match strict.open("libc.so.6") {
+        Err(LoadError::MissingAbiVersion { .. }) => {}
+        _ => panic!("Invalid result"),
+    }
+    assert!(loader.naming(Naming::new("lib", ".so").version("6")).load("c").is_ok());
+}
+
+#[test]
+fn abi_version_test() {
+    let current = AbiVersion::current();
+    assert_eq!(current.header_version, HEADER_VERSION);
+    assert_eq!(current.pointer_width as usize, std::mem::size_of::<usize>());
+
+    let err = LoadError::AbiMismatch {
+        path: std::path::PathBuf::from("/plugins/libstale.so"),
+        expected: current,
+        found: AbiVersion {
+            header_version: HEADER_VERSION + 1,
+            ..current
+        },
+    };
+    assert!(err.to_string().contains("/plugins/libstale.so"));
+
+    assert!(current.is_compatible(&current));
+    let private = AbiVersion {
+        allocator: ALLOCATOR_PRIVATE,
+        ..current
+    };
+    assert!(!private.is_compatible(&private));
+}
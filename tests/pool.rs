@@ -0,0 +1,61 @@
+#![cfg(feature = "import")]
+
+use dy::*;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread;
+use std::time::Duration;
+
+struct ThreadWaker(thread::Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+fn block_on<F: Future>(fut: F) -> F::Output {
+    let mut fut = Box::pin(fut);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match fut.as_mut().poll(&mut cx) {
+            Poll::Ready(rtn) => return rtn,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+#[test]
+fn max_workers_test() {
+    set_max_workers(2);
+    let running = Arc::new(AtomicUsize::new(0));
+    let most = Arc::new(AtomicUsize::new(0));
+    let (r, m) = (running.clone(), most.clone());
+    let module = Arc::new(MockModule::new().function("work", move |args| {
+        let now = r.fetch_add(1, Ordering::SeqCst) + 1;
+        m.fetch_max(now, Ordering::SeqCst);
+        thread::sleep(Duration::from_millis(50));
+        r.fetch_sub(1, Ordering::SeqCst);
+        args[0].copy()
+    }));
+
+    let futures: Vec<_> = (0..6)
+        .map(|i| ModuleApi::call_async(&module, "work", vec![Value::new_int(i)]))
+        .collect();
+    let res: Vec<i64> = block_on(async {
+        let mut res = Vec::new();
+        for fut in futures {
+            res.push(fut.await.as_int().unwrap().get());
+        }
+        res
+    });
+    assert_eq!(res, vec![0, 1, 2, 3, 4, 5]);
+    assert_eq!(most.load(Ordering::SeqCst), 2);
+
+    let rtn = block_on(ModuleApi::call_async(&module, "missing", vec![]));
+    let err = CallError::from_value(&rtn).unwrap();
+    assert_eq!(err.message(), "no function named `missing`");
+}
@@ -0,0 +1,81 @@
+use dy::codec::encode;
+use dy::text::*;
+use dy::*;
+
+#[test]
+fn text_round_trip_test() {
+    let val = Value::new_map(vec![
+        ("null", Value::new_null()),
+        ("bool", Value::new_bool(true)),
+        ("int", Value::new_int(i64::MIN)),
+        ("float", Value::new_float(2.0)),
+        ("nan", Value::new_float(f64::NAN)),
+        ("inf", Value::new_float(f64::NEG_INFINITY)),
+        ("str", Value::new_str("\"Hello\",\n世界\u{1}")),
+        ("bool_arr", Value::new_bool_arr(&[true, false])),
+        ("bytes", Value::new_bytes(&[0, 1, 255])),
+        ("int_arr", Value::new_int_arr(&[-1, 2])),
+        ("float_arr", Value::new_float_arr(&[-0.0, 1e300])),
+        (
+            "arr",
+            Value::new_arr(vec![Value::new_int(1), Value::new_arr(Vec::new())]),
+        ),
+        ("map", Value::new_map(Vec::new())),
+    ]);
+    for printed in [to_string(&val), to_string_pretty(&val)].iter() {
+        let parsed = parse(printed).unwrap();
+        assert_eq!(encode(&parsed), encode(&val), "{}", printed);
+    }
+
+    let parsed = parse("{a: i[1, 2,], \"b\": x\"0aFF\", c: [2.0, -inf,],}").unwrap();
+    assert_eq!(
+        to_string(&parsed),
+        "{\"a\": i[1, 2], \"b\": x\"0aff\", \"c\": [2.0, -inf]}"
+    );
+    assert_eq!(
+        to_string_pretty(&parse("[1, {\"k\": []}]").unwrap()),
+        "[\n  1,\n  {\n    \"k\": []\n  }\n]"
+    );
+    assert_eq!(
+        to_json(&parse("{k: [b[true], x\"ff\", f[nan, 1.5]]}").unwrap()),
+        "{\"k\": [[true], [255], [null, 1.5]]}"
+    );
+    let s = parse("\"\\u00e9\\ud83d\\ude00\"").unwrap();
+    assert_eq!(s.as_str().unwrap().get(), "é😀");
+    assert!(parse("1").unwrap().as_int().is_some());
+    assert!(parse("1e2").unwrap().as_float().is_some());
+    let big = parse("-99999999999999999999").unwrap();
+    assert_eq!(big.as_float().unwrap().get(), -1e20);
+}
+
+#[test]
+fn text_error_test() {
+    let err = parse("[1, 2").unwrap_err();
+    assert_eq!(err.offset, 5);
+    assert!(parse("").is_err());
+    assert!(parse("1 2").is_err());
+    assert!(parse("{a 1}").is_err());
+    assert!(parse("i[1.5]").is_err());
+    assert!(parse("x\"abc\"").is_err());
+    assert!(parse("\"\\u0000\"").is_err());
+    assert!(parse("\"\\u+041\"").is_err());
+    assert!(parse("i[99999999999999999999]").is_err());
+    assert!(parse(&"[".repeat(MAX_DEPTH + 1)).is_err());
+}
+
+#[test]
+fn text_variable_test() {
+    let vars = |name: &str| match name {
+        "1" => Some(Value::new_int_arr(&[1, 2])),
+        "config" => Some(Value::new_map(vec![("k", Value::new_null())])),
+        _ => None,
+    };
+    let val = parse_with("[$1, {c: $config}]", vars).unwrap();
+    assert_eq!(to_string(&val), "[i[1, 2], {\"c\": {\"k\": null}}]");
+
+    let err = parse_with("[$1, $2]", vars).unwrap_err();
+    assert_eq!(err.offset, 5);
+    assert_eq!(err.message, "unknown variable `$2`");
+    assert!(parse("$1").is_err());
+    assert!(parse_with("$", vars).is_err());
+}
@@ -104,4 +104,73 @@ fn as_test() {
             _ => panic!("Invalid type"),
         }
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn function_info_test() {
+    let infos = vec![
+        FunctionInfo {
+            name: String::from("scale"),
+            arity: Some(2),
+            signature: Some(String::from("(x: f64, k: f64) -> f64")),
+            doc: Some(String::from("Scales `x` by `k`")),
+        },
+        FunctionInfo::new("variadic"),
+    ];
+    let val = FunctionInfo::list_to_value(&infos);
+    assert_eq!(FunctionInfo::list_from_value(&val), infos);
+}
+
+#[test]
+fn call_error_test() {
+    let err = CallError::Panic {
+        message: String::from("index out of bounds"),
+        location: Some(String::from("src/lib.rs:3:5")),
+    };
+    assert_eq!(CallError::from_value(&err.to_value()), Some(err));
+
+    let err = CallError::from("invalid argument");
+    assert_eq!(err.location(), None);
+    assert_eq!(CallError::from_value(&err.to_value()), Some(err));
+
+    let err = CallError::Argument {
+        message: String::from("argument `x`: expected Int, found Str"),
+    };
+    assert_eq!(err.to_string(), err.message());
+    assert_eq!(CallError::from_value(&err.to_value()), Some(err));
+
+    let err = CallError::timeout(std::time::Duration::from_millis(1500));
+    assert_eq!(err.message(), "timed out after 1.5s");
+    assert_eq!(CallError::from_value(&err.to_value()), Some(err));
+
+    assert_eq!(CallError::from_value(&Value::new_map(vec![])), None);
+}
+
+#[test]
+fn shared_value_test() {
+    let val = Value::new_arr(vec![Value::new_int(1), Value::new_str("two")]);
+    let val = std::thread::spawn(move || val).join().unwrap();
+    assert_eq!(val.as_arr().unwrap().len(), 2);
+
+    let shared = SharedValue::new(val);
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let shared = shared.clone();
+            std::thread::spawn(move || {
+                let arr = shared.as_arr().unwrap();
+                arr.at(1).unwrap().as_str().unwrap().get()
+            })
+        })
+        .collect();
+    for reader in readers {
+        assert_eq!(reader.join().unwrap(), "two");
+    }
+
+    let clone = shared.clone();
+    assert!(SharedValue::ptr_eq(&shared, &clone));
+    let copied = clone.into_owned();
+    assert_eq!(copied.as_arr().unwrap().len(), 2);
+    let owned = shared.into_owned();
+    let first = owned.as_arr().unwrap().at(0).unwrap();
+    assert_eq!(first.as_int().unwrap().get(), 1);
+}